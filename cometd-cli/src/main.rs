@@ -0,0 +1,296 @@
+use cometd::{
+    async_trait,
+    client::Context,
+    packet::{
+        Channel,
+        Packet,
+    },
+    CometError,
+    CometResult,
+};
+use std::{
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Mutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    io::{
+        AsyncBufReadExt,
+        BufReader,
+    },
+    sync::oneshot,
+};
+
+/// How long to wait for the server to acknowledge a subscription
+const SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const USAGE: &str = "\
+Usage: cometd-cli <url> [options]
+
+Options:
+    -s, --subscribe <channel>         Subscribe to a channel or pattern, like /chat/**
+    -p, --publish <channel> <json>    Publish a message once connected
+    -v, --verbose                     Print meta traffic to stderr
+    -h, --help                        Print this message
+
+Incoming messages are printed to stdout as JSON lines.
+Once connected and subscribed, lines read from stdin are published as '<channel> <json>'.";
+
+/// Command line options
+#[derive(Debug)]
+struct Options {
+    url: String,
+    subscriptions: Vec<String>,
+    publish: Vec<(String, serde_json::Value)>,
+    verbose: bool,
+}
+
+impl Options {
+    /// Parse options from args, not including the program name
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut url = None;
+        let mut subscriptions = Vec::new();
+        let mut publish = Vec::new();
+        let mut verbose = false;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-s" | "--subscribe" => {
+                    let channel = args
+                        .next()
+                        .ok_or_else(|| format!("missing channel for '{}'", arg))?;
+                    subscriptions.push(channel);
+                }
+                "-p" | "--publish" => {
+                    let channel = args
+                        .next()
+                        .ok_or_else(|| format!("missing channel for '{}'", arg))?;
+                    let data = args
+                        .next()
+                        .ok_or_else(|| format!("missing data for '{}'", arg))?;
+                    let data = serde_json::from_str(&data)
+                        .map_err(|e| format!("invalid json '{}': {}", data, e))?;
+                    publish.push((channel, data));
+                }
+                "-v" | "--verbose" => {
+                    verbose = true;
+                }
+                "-h" | "--help" => {
+                    return Err(String::new());
+                }
+                _ if arg.starts_with('-') => {
+                    return Err(format!("unknown option '{}'", arg));
+                }
+                _ if url.is_none() => {
+                    url = Some(arg);
+                }
+                _ => {
+                    return Err(format!("unexpected argument '{}'", arg));
+                }
+            }
+        }
+
+        Ok(Self {
+            url: url.ok_or_else(|| String::from("missing url"))?,
+            subscriptions,
+            publish,
+            verbose,
+        })
+    }
+}
+
+struct CliHandler {
+    options: Options,
+
+    /// Whether the messages passed as arguments were published
+    published: AtomicBool,
+
+    /// Fired once the first connection is set up, to start reading stdin
+    ready: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+#[async_trait]
+impl cometd::client::Handler for CliHandler {
+    async fn on_reconnect(&self, ctx: Context, resumed: bool) {
+        if self.options.verbose {
+            eprintln!("# connected (resumed: {})", resumed);
+        }
+
+        if !resumed {
+            for channel in self.options.subscriptions.iter() {
+                if let Err(e) = subscribe(&ctx, channel).await {
+                    eprintln!("# failed to subscribe to '{}': {}", channel, e);
+                }
+            }
+        }
+
+        if !self.published.swap(true, Ordering::SeqCst) {
+            for (channel, data) in self.options.publish.iter() {
+                if let Err(e) = ctx.publish(channel, data.clone()).await {
+                    eprintln!("# failed to publish to '{}': {}", channel, e);
+                }
+            }
+        }
+
+        if let Some(ready) = self.ready.lock().unwrap().take() {
+            let _ = ready.send(());
+        }
+    }
+
+    async fn on_error(&self, _ctx: Context, error: CometError) {
+        // `subscribe` reports these with the channel it was asked for
+        if let CometError::SubscribeFailed { .. } = error {
+            return;
+        }
+
+        eprintln!("# error: {}", error);
+    }
+
+    async fn on_message(&self, _ctx: Context, packet: Packet) {
+        print_packet(&packet);
+    }
+
+    async fn on_meta(&self, _ctx: Context, packet: Packet) {
+        if self.options.verbose {
+            match serde_json::to_string(&packet) {
+                Ok(json) => eprintln!("{}", json),
+                Err(e) => eprintln!("# failed to serialize packet: {}", e),
+            }
+        }
+    }
+}
+
+/// Subscribe to a channel, waiting for the server to accept or reject it
+async fn subscribe(ctx: &Context, channel: &str) -> CometResult<()> {
+    let subscription: Channel = channel.parse()?;
+    let packet = Packet::new()
+        .channel(Channel::Subscribe)
+        .client_id(ctx.get_client_id().ok_or(CometError::MissingClientId)?)
+        .subscription(subscription.clone());
+
+    let reply = ctx
+        .request(
+            packet,
+            Channel::Subscribe.as_str(),
+            move |reply| reply.subscription.as_ref() == Some(&subscription),
+            SUBSCRIBE_TIMEOUT,
+        )
+        .await?;
+
+    if reply.successful != Some(true) {
+        return Err(CometError::SubscribeFailed {
+            subscription: channel.to_string(),
+            error: reply.error.unwrap_or_default(),
+        });
+    }
+
+    Ok(())
+}
+
+fn print_packet(packet: &Packet) {
+    match serde_json::to_string(packet) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("# failed to serialize packet: {}", e),
+    }
+}
+
+/// Publish lines of the form '<channel> <json>' from stdin until it closes
+async fn publish_stdin(ctx: Context) {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("# failed to read stdin: {}", e);
+                break;
+            }
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (channel, data) = match line.find(char::is_whitespace) {
+            Some(i) => (&line[..i], line[i..].trim_start()),
+            None => {
+                eprintln!("# expected '<channel> <json>'");
+                continue;
+            }
+        };
+
+        let data = match serde_json::from_str(data) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("# invalid json: {}", e);
+                continue;
+            }
+        };
+
+        if let Err(e) = ctx.publish(channel, data).await {
+            eprintln!("# failed to publish to '{}': {}", channel, e);
+        }
+    }
+}
+
+#[tokio::main(threaded_scheduler)]
+async fn main() {
+    let options = match Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("error: {}\n", e);
+            }
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    let url = options.url.clone();
+    let (ready, ready_rx) = oneshot::channel();
+    let handler = CliHandler {
+        options,
+        published: AtomicBool::new(false),
+        ready: Mutex::new(Some(ready)),
+    };
+
+    let mut client = match cometd::Client::connect_with_handler(&url, handler).await {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("# failed to connect to '{}': {}", url, e);
+            std::process::exit(1);
+        }
+    };
+
+    // Lines read before the handshake would have no client id to be published with
+    let ctx = client.context();
+    tokio::spawn(async move {
+        if ready_rx.await.is_ok() {
+            publish_stdin(ctx).await;
+        }
+    });
+
+    let cancel_handle = client.cancel_handle();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel_handle.cancel();
+        }
+    });
+
+    match client.run().await {
+        Ok(reason) => {
+            eprintln!("# exited: {:?}", reason);
+        }
+        Err(e) => {
+            eprintln!("# exited with error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,18 @@
+#![no_main]
+
+use cometd::packet::decode_frame;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let frame = match std::str::from_utf8(data) {
+        Ok(frame) => frame,
+        Err(_) => return,
+    };
+
+    // Anything that decodes must survive a round trip
+    if let Ok(packets) = decode_frame(frame) {
+        let encoded = serde_json::to_string(&packets).expect("encode packets");
+        let decoded = decode_frame(&encoded).expect("decode encoded packets");
+        assert_eq!(packets, decoded);
+    }
+});
@@ -1,29 +1,90 @@
+mod cancel;
 mod context;
 mod handler;
+mod reconnect;
 
 pub use self::{
+    cancel::CancelHandle,
     context::Context,
     handler::{
         DefaultHandler,
+        ErrorPolicy,
+        Fallible,
         Handler,
+        TryHandler,
     },
+    reconnect::ReconnectPolicy,
 };
 use crate::{
     packet::{
         Channel,
         Packet,
+        Reconnect,
+    },
+    transport::{
+        Incoming,
+        WsTransport,
     },
-    transport::WsTransport,
     CometError,
     CometResult,
 };
-use std::sync::Arc;
+use std::{
+    future::Future,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time::Interval;
 use tungstenite::error::Error as TError;
 
+/// How often a websocket ping is sent to the server
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long to wait past the advised timeout before a silent connection is considered dead
+const HEARTBEAT_GRACE: Duration = Duration::from_secs(10);
+
+/// The reason a client's run loop exited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The server closed the connection
+    ServerClosed,
+
+    /// The client was shut down locally, through `Context::shutdown` or `Client::graceful_shutdown`
+    Shutdown,
+
+    /// The websocket stream ended without a close frame
+    StreamEnded,
+
+    /// The client was stopped through a `CancelHandle`
+    Cancelled,
+}
+
+/// Spawn a handler task.
+///
+/// With the `tracing` feature, the task runs in the span that spawned it.
+pub(crate) fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    #[cfg(feature = "tracing")]
+    let future = tracing::Instrument::in_current_span(future);
+
+    tokio::spawn(future);
+}
+
+/// An event that wakes up the run loop
+enum LoopEvent {
+    Incoming(CometResult<Incoming>),
+    Heartbeat,
+    Cancelled,
+}
+
 /// A cometd client
 pub struct Client<T> {
+    url: String,
     ctx: Context,
     transport: WsTransport,
+    heartbeat: Interval,
+    reconnect_policy: ReconnectPolicy,
 
     /// The event handler
     pub handler: Arc<T>,
@@ -42,8 +103,11 @@ impl<T: Handler + 'static> Client<T> {
         let (stream, _response) = tokio_tungstenite::connect_async(url).await?;
         let transport = WsTransport::new(stream);
         let client = Client {
+            url: url.to_string(),
             ctx: Context::new(transport.clone()),
             transport,
+            heartbeat: tokio::time::interval(PING_INTERVAL),
+            reconnect_policy: ReconnectPolicy::default(),
 
             handler: Arc::new(handler),
         };
@@ -53,22 +117,88 @@ impl<T: Handler + 'static> Client<T> {
         Ok(client)
     }
 
-    /// Run client
-    pub async fn run(&mut self) -> CometResult<()> {
+    /// Set how the client re-opens its websocket after the connection dies
+    pub fn set_reconnect_policy(&mut self, reconnect_policy: ReconnectPolicy) {
+        self.reconnect_policy = reconnect_policy;
+    }
+
+    /// Get a context for this client
+    pub fn context(&self) -> Context {
+        self.ctx.clone()
+    }
+
+    /// Get a handle that can cancel this client from another task
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.ctx.cancel_handle()
+    }
+
+    /// Run client until it exits, returning the reason it stopped
+    pub async fn run(&mut self) -> CometResult<ExitReason> {
+        #[cfg(feature = "tracing")]
+        let result = {
+            let span = tracing::info_span!("cometd_client", url = %self.url);
+            tracing::Instrument::instrument(self.run_loop(), span).await
+        };
+
+        #[cfg(not(feature = "tracing"))]
+        let result = self.run_loop().await;
+
+        // Nothing will arrive for anyone still waiting
+        self.ctx.clear_waiters();
+
+        result
+    }
+
+    async fn run_loop(&mut self) -> CometResult<ExitReason> {
         loop {
-            let next_packet = self.transport.next_packet().await;
+            let event = tokio::select! {
+                next_packet = self.transport.next_packet() => LoopEvent::Incoming(next_packet),
+                _ = self.heartbeat.tick() => LoopEvent::Heartbeat,
+                _ = self.ctx.cancel_handle.cancelled() => LoopEvent::Cancelled,
+            };
+
+            let next_packet = match event {
+                LoopEvent::Incoming(next_packet) => next_packet,
+                LoopEvent::Heartbeat => {
+                    if !self.check_heartbeat().await? {
+                        return self.exit_cancelled().await;
+                    }
+                    continue;
+                }
+                LoopEvent::Cancelled => {
+                    return self.exit_cancelled().await;
+                }
+            };
+
             match next_packet {
-                Ok(packets) => {
+                Ok(Incoming::Packets(packets)) => {
                     self.process_packets(packets).await;
                 }
-                Err(e) => match e {
-                    CometError::ClientExited => {
-                        return Ok(());
+                Ok(Incoming::Closed(ExitReason::StreamEnded)) => {
+                    if !self.reconnect_policy.should_retry(0) {
+                        return Ok(ExitReason::StreamEnded);
+                    }
+
+                    if !self.reconnect().await? {
+                        return self.exit_cancelled().await;
                     }
+                }
+                Ok(Incoming::Closed(reason)) => {
+                    return Ok(reason);
+                }
+                Err(e) => match e {
                     CometError::Ws(TError::Io(_)) => {
-                        return Err(e);
+                        if !self.reconnect_policy.should_retry(0) {
+                            return Err(e);
+                        }
+
+                        self.spawn_on_error(e);
+                        if !self.reconnect().await? {
+                            return self.exit_cancelled().await;
+                        }
                     }
                     e => {
+                        self.ctx.record_transport_error();
                         self.handler.on_error(self.ctx.clone(), e).await;
                     }
                 },
@@ -76,68 +206,219 @@ impl<T: Handler + 'static> Client<T> {
         }
     }
 
+    /// Disconnect after a cancel, returning the error the client was stopped with, if any
+    async fn exit_cancelled(&self) -> CometResult<ExitReason> {
+        let disconnect_result = self.ctx.disconnect().await;
+
+        if let Some(e) = self.ctx.take_exit_error() {
+            return Err(e);
+        }
+
+        disconnect_result?;
+        Ok(ExitReason::Cancelled)
+    }
+
+    /// Ping the server, or reconnect if nothing was heard from it for too long.
+    ///
+    /// Returns false if the client was cancelled while reconnecting.
+    async fn check_heartbeat(&mut self) -> CometResult<bool> {
+        let deadline = self.ctx.get_timeout() + HEARTBEAT_GRACE;
+        let silence = self.transport.last_seen().elapsed();
+
+        if silence > deadline {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(?silence, "heartbeat timed out");
+
+            let e = CometError::HeartbeatTimeout(silence);
+            if !self.reconnect_policy.should_retry(0) {
+                return Err(e);
+            }
+
+            self.spawn_on_error(e);
+            return self.reconnect().await;
+        }
+
+        if let Err(e) = self.transport.send_ping().await {
+            self.spawn_on_error(e);
+        }
+
+        Ok(true)
+    }
+
+    /// Tear down the socket and open a new one to the same url, following the reconnect policy.
+    ///
+    /// The old session is resumed if the server still knows it, otherwise a new handshake is made.
+    /// Returns false if the client was cancelled before a new socket was opened,
+    /// or the last error if the policy ran out of attempts.
+    async fn reconnect(&mut self) -> CometResult<bool> {
+        self.transport.abort().await;
+
+        let mut attempts = 0;
+        loop {
+            if self.ctx.cancel_handle.is_cancelled() {
+                return Ok(false);
+            }
+
+            attempts += 1;
+            match self.try_reconnect().await {
+                Ok(()) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::info!(attempts, "reopened socket");
+
+                    return Ok(true);
+                }
+                Err(e) if !self.reconnect_policy.should_retry(attempts) => return Err(e),
+                Err(e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(attempts, error = %e, "failed to reopen socket");
+
+                    self.spawn_on_error(e);
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::delay_for(self.reconnect_policy.delay(attempts)) => {}
+                _ = self.ctx.cancel_handle.cancelled() => return Ok(false),
+            }
+        }
+    }
+
+    async fn try_reconnect(&self) -> CometResult<()> {
+        let (stream, _response) = tokio_tungstenite::connect_async(self.url.as_str()).await?;
+        self.transport.replace_stream(stream).await;
+        self.ctx.resume_session().await
+    }
+
+    fn spawn_on_error(&self, e: CometError) {
+        self.ctx.record_transport_error();
+
+        let handler = self.handler.clone();
+        let ctx = self.ctx.clone();
+
+        spawn(async move { handler.on_error(ctx, e).await });
+    }
+
     async fn process_packets(&mut self, packets: Vec<Packet>) {
         for packet in packets {
+            #[cfg(feature = "tracing")]
+            let span = tracing::debug_span!(
+                "packet",
+                channel = %packet.channel.as_str(),
+                client_id = %self.ctx.get_client_id().unwrap_or_default(),
+            );
+            #[cfg(feature = "tracing")]
+            let _enter = span.enter();
+
+            if let Some(timeout) = packet.advice.as_ref().and_then(|advice| advice.timeout) {
+                self.ctx.inner.lock().unwrap().timeout = timeout;
+            }
+
+            self.ctx.notify_waiters(&packet);
+
+            if packet.channel.is_meta() {
+                let handler = self.handler.clone();
+                let ctx = self.ctx.clone();
+                let packet = packet.clone();
+
+                spawn(async move { handler.on_meta(ctx, packet).await });
+            }
+
             match packet.channel {
                 Channel::Handshake => {
                     if let (Some(true), Some(client_id)) = (packet.successful, packet.client_id) {
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!("handshake successful");
+
                         {
                             let mut lock = self.ctx.inner.lock().unwrap();
                             lock.client_id = Some(client_id);
                             lock.is_reconnect = true;
+                            lock.is_resume = false;
                         }
 
-                        if let Err(e) = self.ctx.queue_connect() {
-                            let handler = self.handler.clone();
-                            let ctx = self.ctx.clone();
+                        self.ctx.record_handshake();
 
-                            tokio::spawn(async move {
-                                handler.on_error(ctx, e).await;
-                            });
+                        if let Err(e) = self.ctx.queue_connect() {
+                            self.spawn_on_error(e);
                         }
                     } else {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(error = ?packet.error, "handshake failed");
+
                         self.ctx.queue_handshake();
                     }
                 }
                 Channel::Connect => {
+                    self.transport.mark_alive();
+
                     if packet.successful == Some(false) {
-                        self.ctx.queue_handshake();
+                        let retry = packet
+                            .advice
+                            .as_ref()
+                            .and_then(|advice| advice.reconnect.as_ref())
+                            == Some(&Reconnect::Retry);
+
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(error = ?packet.error, retry, "connect failed");
+
+                        // A session the server no longer knows about needs a new handshake,
+                        // which will queue its own connect.
+                        if !retry {
+                            self.ctx.queue_handshake();
+                            continue;
+                        }
                     } else {
-                        let is_reconnect = {
+                        let reconnect = {
                             let mut lock = self.ctx.inner.lock().unwrap();
                             if lock.is_reconnect {
                                 lock.is_reconnect = false;
-                                true
+                                Some(std::mem::replace(&mut lock.is_resume, false))
                             } else {
-                                false
+                                None
                             }
                         };
 
-                        if is_reconnect {
+                        if let Some(resumed) = reconnect {
+                            #[cfg(feature = "tracing")]
+                            tracing::info!(resumed, "connected");
+
                             let handler = self.handler.clone();
                             let ctx = self.ctx.clone();
 
-                            tokio::spawn(async move { handler.on_reconnect(ctx).await });
+                            spawn(async move { handler.on_reconnect(ctx, resumed).await });
                         }
                     }
 
                     // TODO: Find out why this has to be here
                     if let Err(e) = self.ctx.queue_connect() {
-                        let handler = self.handler.clone();
-                        let ctx = self.ctx.clone();
-
-                        tokio::spawn(async move { handler.on_error(ctx, e).await });
+                        self.spawn_on_error(e);
                     }
                 }
                 Channel::Subscribe => {
-                    // TODO: Figure out how to handle failed subscriptions
-                    assert_eq!(packet.successful, Some(true));
+                    // Waiters already saw the reply, so only tell the handler about failures
+                    if packet.successful != Some(true) {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(
+                            subscription = ?packet.subscription,
+                            error = ?packet.error,
+                            "subscribe failed",
+                        );
+
+                        self.spawn_on_error(CometError::SubscribeFailed {
+                            subscription: packet
+                                .subscription
+                                .as_ref()
+                                .map(|channel| channel.as_str().to_string())
+                                .unwrap_or_default(),
+                            error: packet.error.unwrap_or_default(),
+                        });
+                    }
                 }
                 _ => {
                     let handler = self.handler.clone();
                     let ctx = self.ctx.clone();
 
-                    tokio::spawn(async move {
+                    spawn(async move {
                         handler.on_message(ctx, packet).await;
                     });
                 }
@@ -145,15 +426,12 @@ impl<T: Handler + 'static> Client<T> {
         }
 
         if let Err(e) = self.ctx.send_buffered_packets().await {
-            let handler = self.handler.clone();
-            let ctx = self.ctx.clone();
-
-            tokio::spawn(async move { handler.on_error(ctx, e).await });
+            self.spawn_on_error(e);
         }
     }
 
+    /// Send a disconnect packet and close the connection
     pub async fn graceful_shutdown(&self) -> CometResult<()> {
-        // TODO: Unsub, disconnect packet
-        self.transport.graceful_shutdown().await
+        self.ctx.disconnect().await
     }
 }
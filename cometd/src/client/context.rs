@@ -1,4 +1,6 @@
 use crate::{
+    client::CancelHandle,
+    metrics::MetricsSnapshot,
     packet::{
         Advice,
         Channel,
@@ -9,15 +11,23 @@ use crate::{
     CometError,
     CometResult,
 };
-use std::sync::{
-    Arc,
-    Mutex,
+use std::{
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::Duration,
 };
+use tokio::sync::oneshot;
+
+/// The connect timeout requested in handshakes, in milliseconds
+const DEFAULT_TIMEOUT: u64 = 60_000;
 
 #[derive(Clone)]
 pub struct Context {
     pub(crate) inner: Arc<Mutex<ContextState>>,
     transport: WsTransport, // TODO: Replace with request buffer?
+    pub(crate) cancel_handle: CancelHandle,
 }
 
 impl Context {
@@ -26,10 +36,16 @@ impl Context {
             inner: Arc::new(Mutex::new(ContextState {
                 client_id: None,
                 is_reconnect: true,
+                is_resume: false,
+                timeout: DEFAULT_TIMEOUT,
+                exit_error: None,
+                waiters: Vec::new(),
+                next_waiter_id: 0,
 
                 request_buffer: Vec::new(),
             })),
             transport,
+            cancel_handle: CancelHandle::new(),
         }
     }
 
@@ -64,7 +80,7 @@ impl Context {
             .version("1.0".to_string())
             .minimum_version("1.0".to_string())
             .supported_connection_types(vec![ConnectionType::WebSocket])
-            .advice(Advice::new().timeout(60_000).interval(0));
+            .advice(Advice::new().timeout(DEFAULT_TIMEOUT).interval(0));
 
         self.send_packet(handshake_packet).await
     }
@@ -76,7 +92,7 @@ impl Context {
             .version("1.0".to_string())
             .minimum_version("1.0".to_string())
             .supported_connection_types(vec![ConnectionType::WebSocket])
-            .advice(Advice::new().timeout(60_000).interval(0));
+            .advice(Advice::new().timeout(DEFAULT_TIMEOUT).interval(0));
 
         self.queue_packet(handshake_packet)
     }
@@ -106,11 +122,28 @@ impl Context {
         Ok(())
     }
 
+    /// Subscribe to a channel or channel pattern.
+    ///
+    /// Errors without sending anything if the name is not a valid Bayeux channel.
     pub async fn subscribe(&self, s: &str) -> CometResult<()> {
+        let subscription: Channel = s.parse()?;
         let packet = Packet::new()
             .channel(Channel::Subscribe)
             .client_id(self.get_client_id().ok_or(CometError::MissingClientId)?)
-            .subscription(s.into());
+            .subscription(subscription);
+
+        self.send_packet(packet).await
+    }
+
+    /// Publish data to a channel.
+    ///
+    /// Errors without sending anything if the name is not a valid Bayeux channel.
+    pub async fn publish(&self, channel: &str, data: serde_json::Value) -> CometResult<()> {
+        let channel: Channel = channel.parse()?;
+        let packet = Packet::new()
+            .channel(channel)
+            .client_id(self.get_client_id().ok_or(CometError::MissingClientId)?)
+            .data(data);
 
         self.send_packet(packet).await
     }
@@ -119,14 +152,232 @@ impl Context {
         self.transport.graceful_shutdown().await
     }
 
+    /// Send a disconnect packet, if a session was established, and close the connection.
+    ///
+    /// The connection is closed even if sending the disconnect packet fails.
+    pub async fn disconnect(&self) -> CometResult<()> {
+        let send_result = match self.get_client_id() {
+            Some(client_id) => {
+                let packet = Packet::new()
+                    .channel(Channel::Disconnect)
+                    .client_id(client_id);
+
+                self.send_packet(packet).await
+            }
+            None => Ok(()),
+        };
+
+        match self.shutdown().await {
+            Ok(()) | Err(CometError::ClientExited) => {}
+            Err(e) => return Err(e),
+        }
+
+        match send_result {
+            Ok(()) | Err(CometError::ClientExited) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn get_client_id(&self) -> Option<String> {
         self.inner.lock().unwrap().client_id.as_ref().cloned()
     }
+
+    /// Get the connect timeout last advised by the server
+    pub fn get_timeout(&self) -> Duration {
+        Duration::from_millis(self.inner.lock().unwrap().timeout)
+    }
+
+    /// Wait for a message on a channel or channel pattern that satisfies the predicate.
+    ///
+    /// Messages are matched by the client's dispatch loop as they arrive, before and regardless of the `Handler`,
+    /// so this works from inside handler callbacks. Only messages received after this is called are considered.
+    /// The predicate is run on the dispatch loop and should be cheap.
+    pub async fn wait_for<F>(
+        &self,
+        channel: &str,
+        predicate: F,
+        timeout: Duration,
+    ) -> CometResult<Packet>
+    where
+        F: Fn(&Packet) -> bool + Send + 'static,
+    {
+        let (id, rx) = self.add_waiter(channel.parse()?, predicate);
+        self.wait_for_waiter(id, rx, timeout).await
+    }
+
+    /// Send a packet, then wait for a reply on a channel or channel pattern that satisfies the matcher.
+    ///
+    /// The reply is matched as in `wait_for`. Matching starts before the packet is sent, so fast replies are not missed.
+    pub async fn request<F>(
+        &self,
+        packet: Packet,
+        reply_channel: &str,
+        reply_matcher: F,
+        timeout: Duration,
+    ) -> CometResult<Packet>
+    where
+        F: Fn(&Packet) -> bool + Send + 'static,
+    {
+        let (id, rx) = self.add_waiter(reply_channel.parse()?, reply_matcher);
+
+        if let Err(e) = self.send_packet(packet).await {
+            self.remove_waiter(id);
+            return Err(e);
+        }
+
+        self.wait_for_waiter(id, rx, timeout).await
+    }
+
+    fn add_waiter<F>(&self, channel: Channel, predicate: F) -> (u64, oneshot::Receiver<Packet>)
+    where
+        F: Fn(&Packet) -> bool + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+
+        let mut lock = self.inner.lock().unwrap();
+        let id = lock.next_waiter_id;
+        lock.next_waiter_id += 1;
+        lock.waiters.push(Waiter {
+            id,
+            channel,
+            predicate: Box::new(predicate),
+            tx,
+        });
+
+        (id, rx)
+    }
+
+    fn remove_waiter(&self, id: u64) {
+        self.inner
+            .lock()
+            .unwrap()
+            .waiters
+            .retain(|waiter| waiter.id != id);
+    }
+
+    async fn wait_for_waiter(
+        &self,
+        id: u64,
+        rx: oneshot::Receiver<Packet>,
+        timeout: Duration,
+    ) -> CometResult<Packet> {
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(packet)) => Ok(packet),
+            Ok(Err(_)) => Err(CometError::ClientExited),
+            Err(_) => {
+                self.remove_waiter(id);
+                Err(CometError::Timeout(timeout))
+            }
+        }
+    }
+
+    /// Hand a packet to every waiter it satisfies
+    pub(crate) fn notify_waiters(&self, packet: &Packet) {
+        // Predicates run without the lock held, in case they touch the context.
+        let waiters = std::mem::take(&mut self.inner.lock().unwrap().waiters);
+        if waiters.is_empty() {
+            return;
+        }
+
+        let mut pending = Vec::with_capacity(waiters.len());
+        for waiter in waiters {
+            if waiter.channel.matches(&packet.channel) && (waiter.predicate)(packet) {
+                let _ = waiter.tx.send(packet.clone());
+            } else {
+                pending.push(waiter);
+            }
+        }
+
+        // Keep waiters that were added while the lock was released
+        let mut lock = self.inner.lock().unwrap();
+        pending.append(&mut lock.waiters);
+        lock.waiters = pending;
+    }
+
+    /// Drop every waiter, failing them with `ClientExited`
+    pub(crate) fn clear_waiters(&self) {
+        self.inner.lock().unwrap().waiters.clear();
+    }
+
+    /// Get a handle that can cancel the client
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel_handle.clone()
+    }
+
+    /// Stop the client, making `Client::run` return the given error.
+    ///
+    /// If the client is already being stopped with an error, the first error is kept.
+    pub fn stop_with_error(&self, error: CometError) {
+        {
+            let mut lock = self.inner.lock().unwrap();
+            if lock.exit_error.is_none() {
+                lock.exit_error = Some(error);
+            }
+        }
+
+        self.cancel_handle.cancel();
+    }
+
+    pub(crate) fn take_exit_error(&self) -> Option<CometError> {
+        self.inner.lock().unwrap().exit_error.take()
+    }
+
+    /// Get a snapshot of the client's metrics
+    pub fn metrics(&self) -> MetricsSnapshot {
+        let queued_packets = self.inner.lock().unwrap().request_buffer.len();
+        self.transport.metrics().snapshot(queued_packets)
+    }
+
+    pub(crate) fn record_handshake(&self) {
+        self.transport.metrics().record_handshake();
+    }
+
+    /// Count an error returned from a handler in the client's metrics
+    pub fn record_handler_error(&self) {
+        self.transport.metrics().record_handler_error();
+    }
+
+    pub(crate) fn record_transport_error(&self) {
+        self.transport.metrics().record_transport_error();
+    }
+
+    /// Pick the session back up on a new socket.
+    ///
+    /// Packets queued for the old socket are dropped.
+    /// If there is a client id, a connect is sent to try to resume it; otherwise a new handshake is sent.
+    pub(crate) async fn resume_session(&self) -> CometResult<()> {
+        let is_resume = {
+            let mut lock = self.inner.lock().unwrap();
+            lock.request_buffer.clear();
+            lock.is_reconnect = true;
+            lock.is_resume = lock.client_id.is_some();
+            lock.is_resume
+        };
+
+        if is_resume {
+            self.send_connect().await
+        } else {
+            self.send_handshake().await
+        }
+    }
 }
 
 pub struct ContextState {
     pub(crate) client_id: Option<String>,
     pub(crate) is_reconnect: bool,
+    pub(crate) is_resume: bool,
+    pub(crate) timeout: u64,
+    pub(crate) exit_error: Option<CometError>,
+    pub(crate) waiters: Vec<Waiter>,
+    pub(crate) next_waiter_id: u64,
 
     pub(crate) request_buffer: Vec<Packet>,
 }
+
+/// A call to `wait_for` or `request` waiting for a matching packet
+pub(crate) struct Waiter {
+    id: u64,
+    channel: Channel,
+    predicate: Box<dyn Fn(&Packet) -> bool + Send>,
+    tx: oneshot::Sender<Packet>,
+}
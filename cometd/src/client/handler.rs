@@ -2,15 +2,124 @@ use crate::{
     client::Context,
     packet::Packet,
     CometError,
+    CometResult,
 };
+#[cfg(not(feature = "tracing"))]
+use log::warn;
+#[cfg(feature = "tracing")]
+use tracing::warn;
 
 #[crate::async_trait]
 pub trait Handler: Send + Sync {
     async fn on_error(&self, _ctx: Context, _error: CometError) {}
-    async fn on_reconnect(&self, _ctx: Context) {}
+    /// Called once a connection is established, after the first handshake and after every reconnect.
+    ///
+    /// `resumed` is true if an existing session survived a dropped socket,
+    /// so subscriptions are still active.
+    async fn on_reconnect(&self, _ctx: Context, _resumed: bool) {}
     async fn on_message(&self, _ctx: Context, _packet: Packet) {}
+
+    /// Called for every packet received on a `/meta/` channel, before the client acts on it
+    async fn on_meta(&self, _ctx: Context, _packet: Packet) {}
 }
 
 pub struct DefaultHandler;
 #[crate::async_trait]
 impl Handler for DefaultHandler {}
+
+/// A handler whose callbacks can fail.
+///
+/// Wrap it in a `Fallible` to use it as a `Handler`; the `ErrorPolicy` decides what happens to its errors.
+#[crate::async_trait]
+pub trait TryHandler: Send + Sync {
+    async fn on_error(&self, _ctx: Context, _error: CometError) {}
+    /// See `Handler::on_reconnect`
+    async fn on_reconnect(&self, _ctx: Context, _resumed: bool) -> CometResult<()> {
+        Ok(())
+    }
+    async fn on_message(&self, _ctx: Context, _packet: Packet) -> CometResult<()> {
+        Ok(())
+    }
+
+    /// See `Handler::on_meta`
+    async fn on_meta(&self, _ctx: Context, _packet: Packet) -> CometResult<()> {
+        Ok(())
+    }
+}
+
+/// What to do with an error returned from a handler callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Log the error and carry on
+    Log,
+
+    /// Pass the error to the handler's `on_error`
+    ForwardToOnError,
+
+    /// Stop the client, making `run` return the error
+    Stop,
+}
+
+/// Adapts a `TryHandler` into a `Handler`, applying an `ErrorPolicy` to its errors
+pub struct Fallible<T> {
+    handler: T,
+    policy: ErrorPolicy,
+}
+
+impl<T: TryHandler> Fallible<T> {
+    /// Wrap a handler, forwarding its errors to its `on_error`
+    pub fn new(handler: T) -> Self {
+        Self {
+            handler,
+            policy: ErrorPolicy::ForwardToOnError,
+        }
+    }
+
+    /// Set the error policy
+    pub fn policy(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Get the wrapped handler
+    pub fn handler(&self) -> &T {
+        &self.handler
+    }
+
+    async fn handle_result(&self, ctx: Context, result: CometResult<()>) {
+        let e = match result {
+            Ok(()) => return,
+            Err(e) => e,
+        };
+
+        ctx.record_handler_error();
+
+        match self.policy {
+            ErrorPolicy::Log => warn!("handler error: {}", e),
+            ErrorPolicy::ForwardToOnError => self.handler.on_error(ctx, e).await,
+            ErrorPolicy::Stop => ctx.stop_with_error(e),
+        }
+    }
+}
+
+#[crate::async_trait]
+impl<T: TryHandler> Handler for Fallible<T> {
+    async fn on_error(&self, ctx: Context, error: CometError) {
+        self.handler.on_error(ctx, error).await;
+    }
+
+    async fn on_reconnect(&self, ctx: Context, resumed: bool) {
+        let result = self.handler.on_reconnect(ctx.clone(), resumed).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_message(&self, ctx: Context, packet: Packet) {
+        let result = self.handler.on_message(ctx.clone(), packet).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_meta(&self, ctx: Context, packet: Packet) {
+        let result = self.handler.on_meta(ctx.clone(), packet).await;
+        self.handle_result(ctx, result).await;
+    }
+}
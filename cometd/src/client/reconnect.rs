@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+/// How a client re-opens its websocket after the connection dies
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// The delay after the first failed attempt
+    pub initial_delay: Duration,
+
+    /// The longest delay between attempts
+    pub max_delay: Duration,
+
+    /// The factor the delay grows by after each failed attempt
+    pub multiplier: f64,
+
+    /// The number of attempts before giving up. `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl ReconnectPolicy {
+    pub fn new() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            max_attempts: None,
+        }
+    }
+
+    /// A policy that never reconnects
+    pub fn disabled() -> Self {
+        Self::new().max_attempts(0)
+    }
+
+    pub fn initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Whether another attempt may be made after `attempts` failed ones
+    pub fn should_retry(&self, attempts: u32) -> bool {
+        match self.max_attempts {
+            Some(max_attempts) => attempts < max_attempts,
+            None => true,
+        }
+    }
+
+    /// The delay to wait after `attempts` failed attempts
+    pub fn delay(&self, attempts: u32) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+
+        if secs.is_finite() && secs < self.max_delay.as_secs_f64() {
+            Duration::from_secs_f64(secs)
+        } else {
+            self.max_delay
+        }
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,8 +1,16 @@
 pub mod client;
+pub mod metrics;
 pub mod packet;
 mod transport;
 
-pub use crate::client::Client;
+pub use crate::{
+    client::{
+        Client,
+        ExitReason,
+        ReconnectPolicy,
+    },
+    metrics::MetricsSnapshot,
+};
 pub use async_trait::async_trait;
 pub use serde_json::json;
 
@@ -30,4 +38,26 @@ pub enum CometError {
     /// The client id is missing
     #[error("missing client id")]
     MissingClientId,
+
+    /// A channel name is invalid
+    #[error("{0}")]
+    InvalidChannel(#[from] crate::packet::InvalidChannelError),
+
+    /// Nothing was heard from the server for too long
+    #[error("no heartbeat from the server in {0:?}")]
+    HeartbeatTimeout(std::time::Duration),
+
+    /// No matching message arrived in time
+    #[error("timed out after {0:?} waiting for a message")]
+    Timeout(std::time::Duration),
+
+    /// The server rejected a subscription
+    #[error("subscribing to '{subscription}' failed: {error}")]
+    SubscribeFailed {
+        /// The channel or pattern that was subscribed to
+        subscription: String,
+
+        /// The error the server replied with
+        error: String,
+    },
 }
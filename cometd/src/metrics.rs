@@ -0,0 +1,270 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::Mutex,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// Traffic counters for a single channel
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelMetrics {
+    /// Packets received on this channel
+    pub packets_in: u64,
+
+    /// Packets sent on this channel
+    pub packets_out: u64,
+
+    /// Bytes of json received on this channel
+    pub bytes_in: u64,
+
+    /// Bytes of json sent on this channel
+    pub bytes_out: u64,
+}
+
+/// A point-in-time copy of a client's metrics
+#[derive(Debug, Clone, Default)]
+pub struct MetricsSnapshot {
+    /// Traffic counters, by channel name
+    pub channels: HashMap<String, ChannelMetrics>,
+
+    /// Successful handshakes
+    pub handshakes: u64,
+
+    /// Successful handshakes after the first, made because a session was lost
+    pub rehandshakes: u64,
+
+    /// The round trip time of the last answered `/meta/connect`.
+    ///
+    /// This includes the time the server held the connect open.
+    pub last_connect_latency: Option<Duration>,
+
+    /// The total round trip time of all answered connects
+    pub connect_latency_sum: Duration,
+
+    /// The number of answered connects
+    pub connect_latency_count: u64,
+
+    /// Packets waiting in the request buffer
+    pub queued_packets: usize,
+
+    /// Errors returned from a `Fallible` handler, whatever its error policy
+    pub handler_errors: u64,
+
+    /// Connection, protocol and server errors the client passed to the handler's `on_error`
+    pub transport_errors: u64,
+}
+
+/// A per-channel counter: its name, help text, and how to read it
+type ChannelCounter = (&'static str, &'static str, fn(&ChannelMetrics) -> u64);
+
+impl MetricsSnapshot {
+    /// Render the metrics in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        let mut channels: Vec<_> = self.channels.iter().collect();
+        channels.sort_by(|a, b| a.0.cmp(b.0));
+
+        let channel_counters: [ChannelCounter; 4] = [
+            ("cometd_packets_in_total", "Packets received", |m| {
+                m.packets_in
+            }),
+            ("cometd_packets_out_total", "Packets sent", |m| {
+                m.packets_out
+            }),
+            ("cometd_bytes_in_total", "Bytes of json received", |m| {
+                m.bytes_in
+            }),
+            ("cometd_bytes_out_total", "Bytes of json sent", |m| {
+                m.bytes_out
+            }),
+        ];
+
+        for (name, help, get) in channel_counters.iter() {
+            write_header(&mut out, name, help, "counter");
+            for (channel, metrics) in channels.iter() {
+                let _ = writeln!(
+                    out,
+                    "{}{{channel=\"{}\"}} {}",
+                    name,
+                    escape_label(channel),
+                    get(metrics)
+                );
+            }
+        }
+
+        write_metric(
+            &mut out,
+            "cometd_handshakes_total",
+            "Successful handshakes",
+            "counter",
+            self.handshakes,
+        );
+        write_metric(
+            &mut out,
+            "cometd_rehandshakes_total",
+            "Successful handshakes after the first",
+            "counter",
+            self.rehandshakes,
+        );
+
+        write_header(
+            &mut out,
+            "cometd_connect_latency_seconds",
+            "Round trip time of connects",
+            "summary",
+        );
+        let _ = writeln!(
+            out,
+            "cometd_connect_latency_seconds_sum {}",
+            self.connect_latency_sum.as_secs_f64()
+        );
+        let _ = writeln!(
+            out,
+            "cometd_connect_latency_seconds_count {}",
+            self.connect_latency_count
+        );
+
+        write_metric(
+            &mut out,
+            "cometd_queued_packets",
+            "Packets waiting in the request buffer",
+            "gauge",
+            self.queued_packets,
+        );
+        write_metric(
+            &mut out,
+            "cometd_handler_errors_total",
+            "Errors returned from handlers",
+            "counter",
+            self.handler_errors,
+        );
+        write_metric(
+            &mut out,
+            "cometd_transport_errors_total",
+            "Connection, protocol and server errors",
+            "counter",
+            self.transport_errors,
+        );
+
+        out
+    }
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn write_metric(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    kind: &str,
+    value: impl std::fmt::Display,
+) {
+    write_header(out, name, help, kind);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Escape a Prometheus label value
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Live metrics shared by a client's transport and context
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    state: Mutex<MetricsState>,
+}
+
+#[derive(Debug, Default)]
+struct MetricsState {
+    snapshot: MetricsSnapshot,
+
+    /// Send times of unanswered connects, by packet id
+    pending_connects: HashMap<String, Instant>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_sent(&self, channel: &str, bytes: usize) {
+        let mut state = self.state.lock().unwrap();
+        let metrics = state.channel(channel);
+        metrics.packets_out += 1;
+        metrics.bytes_out += bytes as u64;
+    }
+
+    pub fn record_received(&self, channel: &str, bytes: usize) {
+        let mut state = self.state.lock().unwrap();
+        let metrics = state.channel(channel);
+        metrics.packets_in += 1;
+        metrics.bytes_in += bytes as u64;
+    }
+
+    pub fn record_handshake(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.snapshot.handshakes += 1;
+        if state.snapshot.handshakes > 1 {
+            state.snapshot.rehandshakes += 1;
+        }
+    }
+
+    pub fn record_connect_sent(&self, id: String) {
+        self.state
+            .lock()
+            .unwrap()
+            .pending_connects
+            .insert(id, Instant::now());
+    }
+
+    pub fn record_connect_reply(&self, id: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(sent) = state.pending_connects.remove(id) {
+            let latency = sent.elapsed();
+            state.snapshot.last_connect_latency = Some(latency);
+            state.snapshot.connect_latency_sum += latency;
+            state.snapshot.connect_latency_count += 1;
+        }
+    }
+
+    /// Forget unanswered connects, which will never be answered on a new socket
+    pub fn clear_pending_connects(&self) {
+        self.state.lock().unwrap().pending_connects.clear();
+    }
+
+    pub fn record_handler_error(&self) {
+        self.state.lock().unwrap().snapshot.handler_errors += 1;
+    }
+
+    pub fn record_transport_error(&self) {
+        self.state.lock().unwrap().snapshot.transport_errors += 1;
+    }
+
+    pub fn snapshot(&self, queued_packets: usize) -> MetricsSnapshot {
+        let mut snapshot = self.state.lock().unwrap().snapshot.clone();
+        snapshot.queued_packets = queued_packets;
+        snapshot
+    }
+}
+
+impl MetricsState {
+    fn channel(&mut self, channel: &str) -> &mut ChannelMetrics {
+        if !self.snapshot.channels.contains_key(channel) {
+            self.snapshot
+                .channels
+                .insert(channel.to_string(), ChannelMetrics::default());
+        }
+
+        self.snapshot.channels.get_mut(channel).unwrap()
+    }
+}
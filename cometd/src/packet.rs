@@ -7,7 +7,10 @@ pub use self::{
         Advice,
         Reconnect,
     },
-    channel::Channel,
+    channel::{
+        Channel,
+        InvalidChannelError,
+    },
     connection_type::ConnectionType,
 };
 use serde::{
@@ -16,8 +19,31 @@ use serde::{
 };
 use std::collections::HashMap;
 
+/// Decode a frame of packets received from a server.
+///
+/// Servers normally send an array of packets, but some send a single packet object instead.
+pub fn decode_frame(frame: &str) -> Result<Vec<Packet>, serde_json::Error> {
+    Ok(decode_frame_sized(frame)?
+        .into_iter()
+        .map(|(packet, _)| packet)
+        .collect())
+}
+
+/// Decode a frame like `decode_frame`, pairing each packet with the length of its json
+pub(crate) fn decode_frame_sized(frame: &str) -> Result<Vec<(Packet, usize)>, serde_json::Error> {
+    let raw: Vec<&serde_json::value::RawValue> = if frame.trim_start().starts_with('{') {
+        vec![serde_json::from_str(frame)?]
+    } else {
+        serde_json::from_str(frame)?
+    };
+
+    raw.into_iter()
+        .map(|raw| Ok((serde_json::from_str(raw.get())?, raw.get().len())))
+        .collect()
+}
+
 /// A Cometd data packet
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Packet {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub advice: Option<Advice>,
@@ -139,6 +165,21 @@ impl Packet {
         self.subscription = Some(channel);
         self
     }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn successful(mut self, successful: bool) -> Self {
+        self.successful = Some(successful);
+        self
+    }
+
+    pub fn error(mut self, error: String) -> Self {
+        self.error = Some(error);
+        self
+    }
 }
 
 impl Default for Packet {
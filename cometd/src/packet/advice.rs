@@ -4,7 +4,7 @@ use serde::{
 };
 use std::collections::HashMap;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Advice {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timeout: Option<u64>,
@@ -42,6 +42,11 @@ impl Advice {
         self.interval = Some(interval);
         self
     }
+
+    pub fn reconnect(mut self, reconnect: Reconnect) -> Self {
+        self.reconnect = Some(reconnect);
+        self
+    }
 }
 
 impl Default for Advice {
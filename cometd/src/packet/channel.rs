@@ -4,30 +4,66 @@ use serde::{
 };
 use std::{
     borrow::Cow,
-    convert::Infallible,
     str::FromStr,
 };
 
 const HANDSHAKE_PATH: &str = "/meta/handshake";
 const CONNECT_PATH: &str = "/meta/connect";
 const SUBSCRIBE_PATH: &str = "/meta/subscribe";
+const UNSUBSCRIBE_PATH: &str = "/meta/unsubscribe";
+const DISCONNECT_PATH: &str = "/meta/disconnect";
+
+const META_PREFIX: &str = "/meta/";
+const SERVICE_PREFIX: &str = "/service/";
+
+const WILDCARD: &str = "*";
+const DEEP_WILDCARD: &str = "**";
+
+/// A channel name that does not follow the Bayeux channel grammar
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum InvalidChannelError {
+    /// Channel names must start with a '/'
+    #[error("channel names must start with '/'")]
+    MissingLeadingSlash,
+
+    /// A segment between two '/' is empty
+    #[error("empty channel segment")]
+    EmptySegment,
+
+    /// A segment contains a character outside of the Bayeux token grammar
+    #[error("invalid character '{0}' in channel segment")]
+    InvalidCharacter(char),
+
+    /// A wildcard was used somewhere other than the last segment
+    #[error("wildcards are only allowed as the last segment")]
+    MisplacedWildcard,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Hash)]
-#[serde(from = "&str", into = "Cow<'static, str>")]
+#[serde(from = "String", into = "Cow<'static, str>")]
 pub enum Channel {
     Handshake,
     Connect,
     Subscribe,
+    Unsubscribe,
+    Disconnect,
     Other(String),
 }
 
 impl Channel {
+    /// Make a channel from a string without validating it
     pub fn from_string(s: String) -> Self {
-        match s.as_str() {
-            HANDSHAKE_PATH => Channel::Handshake,
-            CONNECT_PATH => Channel::Connect,
-            SUBSCRIBE_PATH => Channel::Subscribe,
-            _ => Channel::Other(s),
+        Self::from_known(&s).unwrap_or(Channel::Other(s))
+    }
+
+    fn from_known(s: &str) -> Option<Self> {
+        match s {
+            HANDSHAKE_PATH => Some(Channel::Handshake),
+            CONNECT_PATH => Some(Channel::Connect),
+            SUBSCRIBE_PATH => Some(Channel::Subscribe),
+            UNSUBSCRIBE_PATH => Some(Channel::Unsubscribe),
+            DISCONNECT_PATH => Some(Channel::Disconnect),
+            _ => None,
         }
     }
 
@@ -36,6 +72,8 @@ impl Channel {
             Channel::Handshake => HANDSHAKE_PATH,
             Channel::Connect => CONNECT_PATH,
             Channel::Subscribe => SUBSCRIBE_PATH,
+            Channel::Unsubscribe => UNSUBSCRIBE_PATH,
+            Channel::Disconnect => DISCONNECT_PATH,
             Channel::Other(ref s) => s,
         }
     }
@@ -45,6 +83,8 @@ impl Channel {
             Channel::Handshake => HANDSHAKE_PATH.into(),
             Channel::Connect => CONNECT_PATH.into(),
             Channel::Subscribe => SUBSCRIBE_PATH.into(),
+            Channel::Unsubscribe => UNSUBSCRIBE_PATH.into(),
+            Channel::Disconnect => DISCONNECT_PATH.into(),
             Channel::Other(s) => s.into(),
         }
     }
@@ -52,6 +92,98 @@ impl Channel {
     pub fn into_string(self) -> String {
         self.into_cow().into_owned()
     }
+
+    /// Check that this channel follows the Bayeux channel grammar.
+    ///
+    /// A name is a '/' followed by '/' separated segments of letters, digits, and `-_!~()$@`.
+    /// The last segment may instead be a `*` or `**` wildcard.
+    pub fn validate(&self) -> Result<(), InvalidChannelError> {
+        let s = self.as_str();
+        if !s.starts_with('/') {
+            return Err(InvalidChannelError::MissingLeadingSlash);
+        }
+
+        let mut segments = self.segments().peekable();
+        while let Some(segment) = segments.next() {
+            if segment.is_empty() {
+                return Err(InvalidChannelError::EmptySegment);
+            }
+
+            if segment == WILDCARD || segment == DEEP_WILDCARD {
+                if segments.peek().is_some() {
+                    return Err(InvalidChannelError::MisplacedWildcard);
+                }
+                continue;
+            }
+
+            if let Some(c) = segment.chars().find(|&c| !is_token_char(c)) {
+                return Err(match c {
+                    '*' => InvalidChannelError::MisplacedWildcard,
+                    c => InvalidChannelError::InvalidCharacter(c),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether this channel follows the Bayeux channel grammar
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Whether this is a `/meta/` channel, used by the protocol itself
+    pub fn is_meta(&self) -> bool {
+        self.as_str().starts_with(META_PREFIX)
+    }
+
+    /// Whether this is a `/service/` channel, used for private request/response messages
+    pub fn is_service(&self) -> bool {
+        self.as_str().starts_with(SERVICE_PREFIX)
+    }
+
+    /// Whether this is a broadcast channel, which delivers messages to every subscriber
+    pub fn is_broadcast(&self) -> bool {
+        !self.is_meta() && !self.is_service()
+    }
+
+    /// Whether this is a `*` or `**` channel pattern
+    pub fn is_wildcard(&self) -> bool {
+        match self.segments().last() {
+            Some(segment) => segment == WILDCARD || segment == DEEP_WILDCARD,
+            None => false,
+        }
+    }
+
+    /// Whether a channel is matched by this channel, treated as a pattern.
+    ///
+    /// `*` matches exactly one trailing segment and `**` matches one or more.
+    /// A channel without wildcards only matches itself.
+    pub fn matches(&self, channel: &Channel) -> bool {
+        let mut pattern = self.segments();
+        let mut segments = channel.segments();
+
+        loop {
+            match (pattern.next(), segments.next()) {
+                (Some(DEEP_WILDCARD), Some(_)) => return true,
+                (Some(WILDCARD), Some(_)) => return segments.next().is_none(),
+                (Some(expected), Some(segment)) if expected == segment => {}
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+
+    /// Iterate over the '/' separated segments of this channel
+    pub fn segments(&self) -> impl Iterator<Item = &str> + '_ {
+        let s = self.as_str();
+        s.strip_prefix('/').unwrap_or(s).split('/')
+    }
+}
+
+/// Whether a char is allowed in a segment by the Bayeux token grammar
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-_!~()$@".contains(c)
 }
 
 impl Default for Channel {
@@ -61,20 +193,19 @@ impl Default for Channel {
 }
 
 impl FromStr for Channel {
-    type Err = Infallible;
+    type Err = InvalidChannelError;
+
+    /// Parse and validate a channel
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
-            HANDSHAKE_PATH => Channel::Handshake,
-            CONNECT_PATH => Channel::Connect,
-            SUBSCRIBE_PATH => Channel::Subscribe,
-            _ => Channel::Other(String::from(s)),
-        })
+        let channel = Self::from(s);
+        channel.validate()?;
+        Ok(channel)
     }
 }
 
 impl<'a> From<&'a str> for Channel {
     fn from(s: &'a str) -> Self {
-        Self::from_str(s).unwrap()
+        Self::from_known(s).unwrap_or_else(|| Channel::Other(String::from(s)))
     }
 }
 
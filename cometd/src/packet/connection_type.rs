@@ -11,8 +11,8 @@ const WEBSOCKET_STR: &str = "websocket";
 const LONG_POLLING_STR: &str = "long-polling";
 const CALLBACK_POLLING_STR: &str = "callback-polling";
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(from = "&str", into = "String")]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "String", into = "String")]
 pub enum ConnectionType {
     WebSocket,
     LongPolling,
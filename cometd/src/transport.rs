@@ -1,5 +1,11 @@
 use crate::{
-    packet::Packet,
+    client::ExitReason,
+    metrics::Metrics,
+    packet::{
+        decode_frame_sized,
+        Channel,
+        Packet,
+    },
     CometError,
     CometResult,
 };
@@ -11,12 +17,16 @@ use futures::{
         StreamExt,
     },
 };
-use std::sync::{
-    atomic::{
-        AtomicU64,
-        Ordering,
+use std::{
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+        Mutex,
     },
-    Arc,
+    time::Instant,
 };
 use tokio::{
     io::AsyncWriteExt,
@@ -29,11 +39,25 @@ use tungstenite::Message as TMessage;
 
 type WebSocketStream = tokio_tungstenite::WebSocketStream<TStream<TcpStream, TlsStream<TcpStream>>>;
 
+/// An event read from the transport
+pub(crate) enum Incoming {
+    /// A batch of packets
+    Packets(Vec<Packet>),
+
+    /// The connection closed
+    Closed(ExitReason),
+}
+
 #[derive(Clone)]
 pub(crate) struct WsTransport {
     tx: Arc<TokioMutex<Option<SplitSink<WebSocketStream, TMessage>>>>,
     rx: Arc<TokioMutex<Option<SplitStream<WebSocketStream>>>>,
     packet_id: Arc<AtomicU64>,
+
+    /// The last time the server proved it was alive
+    last_seen: Arc<Mutex<Instant>>,
+
+    metrics: Arc<Metrics>,
 }
 
 impl WsTransport {
@@ -46,15 +70,71 @@ impl WsTransport {
             rx,
             tx,
             packet_id: Arc::new(AtomicU64::new(0)),
+            last_seen: Arc::new(Mutex::new(Instant::now())),
+            metrics: Arc::new(Metrics::new()),
         }
     }
 
+    /// Swap in a new websocket, dropping the old one
+    pub async fn replace_stream(&self, stream: WebSocketStream) {
+        let (tx, rx) = stream.split();
+        *self.tx.lock().await = Some(tx);
+        *self.rx.lock().await = Some(rx);
+        self.metrics.clear_pending_connects();
+        self.mark_alive();
+    }
+
+    /// Drop the websocket without a close handshake. Used when the connection is already dead.
+    pub async fn abort(&self) {
+        self.tx.lock().await.take();
+        self.rx.lock().await.take();
+    }
+
+    pub async fn send_ping(&self) -> CometResult<()> {
+        self.tx
+            .lock()
+            .await
+            .as_mut()
+            .ok_or(CometError::ClientExited)?
+            .send(TMessage::Ping(Vec::new()))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Record that the server is alive
+    pub fn mark_alive(&self) {
+        *self.last_seen.lock().unwrap() = Instant::now();
+    }
+
+    /// The last time the server proved it was alive
+    pub fn last_seen(&self) -> Instant {
+        *self.last_seen.lock().unwrap()
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
     pub async fn send_packet(&self, mut packets: Vec<Packet>) -> CometResult<()> {
+        let mut encoded = Vec::with_capacity(packets.len());
         for packet in packets.iter_mut() {
-            packet.id = Some(self.get_new_packet_id().to_string());
+            let id = self.get_new_packet_id().to_string();
+            if packet.channel == Channel::Connect {
+                self.metrics.record_connect_sent(id.clone());
+            }
+            packet.id = Some(id);
+
+            let json = serde_json::to_string(packet)?;
+            self.metrics
+                .record_sent(packet.channel.as_str(), json.len());
+            encoded.push(json);
         }
 
-        let data = serde_json::to_string(&packets)?;
+        let data = format!("[{}]", encoded.join(","));
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(packets = packets.len(), bytes = data.len(), "sending frame");
 
         self.tx
             .lock()
@@ -67,26 +147,47 @@ impl WsTransport {
         Ok(())
     }
 
-    pub async fn next_packet(&self) -> CometResult<Vec<Packet>> {
+    pub async fn next_packet(&self) -> CometResult<Incoming> {
         loop {
-            let msg = self
-                .rx
-                .lock()
-                .await
-                .as_mut()
-                .ok_or(CometError::ClientExited)?
-                .next()
-                .await
-                .ok_or(CometError::ClientExited)?;
+            let msg = match self.rx.lock().await.as_mut() {
+                Some(rx) => rx.next().await,
+                None => return Ok(Incoming::Closed(ExitReason::Shutdown)),
+            };
+
+            let msg = match msg {
+                Some(msg) => msg,
+                None => return Ok(Incoming::Closed(ExitReason::StreamEnded)),
+            };
 
             match msg {
                 Ok(msg) => match msg {
                     TMessage::Text(txt) => {
-                        return Ok(serde_json::from_str::<Vec<Packet>>(&txt)?);
+                        #[cfg(feature = "tracing")]
+                        tracing::trace!(bytes = txt.len(), "received frame");
+
+                        let packets = decode_frame_sized(&txt)?
+                            .into_iter()
+                            .map(|(packet, bytes)| {
+                                self.metrics.record_received(packet.channel.as_str(), bytes);
+                                if let (Channel::Connect, Some(id)) = (&packet.channel, &packet.id)
+                                {
+                                    self.metrics.record_connect_reply(id);
+                                }
+                                packet
+                            })
+                            .collect();
+
+                        return Ok(Incoming::Packets(packets));
                     }
                     TMessage::Close(_frame) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!(frame = ?_frame, "server closed the connection");
+
                         self.handle_server_shutdown().await?;
-                        return Err(CometError::ClientExited);
+                        return Ok(Incoming::Closed(ExitReason::ServerClosed));
+                    }
+                    TMessage::Pong(_) => {
+                        self.mark_alive();
                     }
                     _ => {}
                 },
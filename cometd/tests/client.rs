@@ -0,0 +1,490 @@
+use cometd::{
+    client::{
+        Context,
+        Handler,
+    },
+    packet::{
+        decode_frame,
+        Advice,
+        Channel,
+        ConnectionType,
+        Packet,
+        Reconnect,
+    },
+    Client,
+    CometError,
+    ExitReason,
+    ReconnectPolicy,
+};
+use futures::{
+    sink::SinkExt,
+    stream::StreamExt,
+};
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    net::TcpListener,
+    sync::mpsc,
+};
+use tungstenite::Message as TMessage;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A channel the test server refuses subscriptions to
+const FORBIDDEN_CHANNEL: &str = "/forbidden";
+
+/// A channel the test server sends published data back on
+const ECHO_CHANNEL: &str = "/echo";
+
+/// What the test server does to a socket once it answered the first connect on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Behaviour {
+    /// Keep serving, holding later connects open
+    Serve,
+
+    /// Send a close frame
+    Close,
+
+    /// Advise a zero timeout, then stop reading so pings go unanswered
+    GoSilent,
+
+    /// Drop the socket without a close frame
+    Drop,
+
+    /// Keep serving, but make clients from other sockets handshake again
+    Forget,
+}
+
+/// Start a bayeux server on a free port, treating each new socket with the next behaviour.
+///
+/// Sockets past the end of `behaviours` are served. Returns the url to connect to.
+async fn start_server(behaviours: Vec<Behaviour>) -> String {
+    let mut listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let url = format!("ws://{}/cometd", listener.local_addr().expect("local addr"));
+
+    tokio::spawn(async move {
+        let mut sockets = 0;
+        loop {
+            let (stream, _) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(_) => return,
+            };
+            let behaviour = behaviours.get(sockets).copied().unwrap_or(Behaviour::Serve);
+            sockets += 1;
+
+            tokio::spawn(serve(stream, behaviour));
+        }
+    });
+
+    url
+}
+
+async fn serve(stream: tokio::net::TcpStream, behaviour: Behaviour) {
+    let mut ws = match tokio_tungstenite::accept_async(stream).await {
+        Ok(ws) => ws,
+        Err(_) => return,
+    };
+
+    let mut handshaken = false;
+    let mut connected = false;
+    while let Some(Ok(msg)) = ws.next().await {
+        let frame = match msg {
+            TMessage::Text(frame) => frame,
+            TMessage::Close(_) => return,
+            _ => continue,
+        };
+
+        for packet in decode_frame(&frame).expect("valid frame") {
+            let reply = Packet::new()
+                .channel(packet.channel.clone())
+                .client_id("test-client".to_string());
+            let reply = match packet.id.clone() {
+                Some(id) => reply.id(id),
+                None => reply,
+            };
+
+            let reply = match packet.channel {
+                Channel::Handshake => {
+                    handshaken = true;
+                    reply
+                        .successful(true)
+                        .version("1.0".to_string())
+                        .supported_connection_types(vec![ConnectionType::WebSocket])
+                        .advice(
+                            Advice::new()
+                                .reconnect(Reconnect::Retry)
+                                .timeout(if behaviour == Behaviour::GoSilent {
+                                    0
+                                } else {
+                                    10_000
+                                })
+                                .interval(0),
+                        )
+                }
+                Channel::Connect if !handshaken && behaviour == Behaviour::Forget => reply
+                    .successful(false)
+                    .error("402::Unknown client".to_string())
+                    .advice(Advice::new().reconnect(Reconnect::Handshake)),
+                // Hold later connects open, like a real server
+                Channel::Connect if connected => continue,
+                Channel::Connect => {
+                    connected = true;
+                    reply.successful(true)
+                }
+                Channel::Subscribe => {
+                    let subscription = packet.subscription.clone().expect("subscription");
+                    if subscription.as_str() == FORBIDDEN_CHANNEL {
+                        reply
+                            .subscription(subscription)
+                            .successful(false)
+                            .error("403::Forbidden".to_string())
+                    } else {
+                        reply.subscription(subscription).successful(true)
+                    }
+                }
+                _ if packet.channel.as_str() == ECHO_CHANNEL => Packet::new()
+                    .channel(packet.channel.clone())
+                    .data(packet.data.clone().unwrap_or_default()),
+                _ => reply.successful(true),
+            };
+
+            let frame = serde_json::to_string(&[reply]).expect("encode reply");
+            if ws.send(TMessage::Text(frame)).await.is_err() {
+                return;
+            }
+
+            match behaviour {
+                Behaviour::Close if connected => {
+                    let _ = ws.close(None).await;
+                }
+                Behaviour::GoSilent if connected => {
+                    futures::future::pending::<()>().await;
+                }
+                Behaviour::Drop if connected => return,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Reports each `on_reconnect`
+struct ReconnectHandler {
+    reconnects: mpsc::UnboundedSender<bool>,
+}
+
+#[cometd::async_trait]
+impl Handler for ReconnectHandler {
+    async fn on_reconnect(&self, _ctx: Context, resumed: bool) {
+        let _ = self.reconnects.send(resumed);
+    }
+}
+
+async fn connect(url: &str) -> (Client<ReconnectHandler>, mpsc::UnboundedReceiver<bool>) {
+    let (reconnects, reconnects_rx) = mpsc::unbounded_channel();
+    let client = Client::connect_with_handler(url, ReconnectHandler { reconnects })
+        .await
+        .expect("connect");
+
+    (client, reconnects_rx)
+}
+
+async fn next_reconnect(reconnects: &mut mpsc::UnboundedReceiver<bool>) -> bool {
+    tokio::time::timeout(TIMEOUT, reconnects.recv())
+        .await
+        .expect("reconnect")
+        .expect("open reconnect channel")
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn cancel() {
+    let url = start_server(vec![Behaviour::Serve]).await;
+    let (mut client, mut reconnects) = connect(&url).await;
+    let cancel_handle = client.cancel_handle();
+    let run = tokio::spawn(async move { client.run().await });
+
+    assert!(!next_reconnect(&mut reconnects).await);
+    cancel_handle.cancel();
+
+    let reason = tokio::time::timeout(TIMEOUT, run)
+        .await
+        .expect("client exit")
+        .expect("join client")
+        .expect("run");
+    assert_eq!(reason, ExitReason::Cancelled);
+    assert!(cancel_handle.is_cancelled());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn server_closed() {
+    let url = start_server(vec![Behaviour::Close]).await;
+    let (mut client, mut reconnects) = connect(&url).await;
+    let run = tokio::spawn(async move { client.run().await });
+
+    assert!(!next_reconnect(&mut reconnects).await);
+
+    let reason = tokio::time::timeout(TIMEOUT, run)
+        .await
+        .expect("client exit")
+        .expect("join client")
+        .expect("run");
+    assert_eq!(reason, ExitReason::ServerClosed);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn heartbeat_timeout() {
+    let url = start_server(vec![Behaviour::GoSilent]).await;
+    let (mut client, mut reconnects) = connect(&url).await;
+    client.set_reconnect_policy(ReconnectPolicy::disabled());
+    let run = tokio::spawn(async move { client.run().await });
+
+    assert!(!next_reconnect(&mut reconnects).await);
+
+    // The heartbeat is checked every 10 seconds, and allows 10 seconds of silence past the advised timeout
+    let result = tokio::time::timeout(Duration::from_secs(30), run)
+        .await
+        .expect("client exit")
+        .expect("join client");
+    match result {
+        Err(CometError::HeartbeatTimeout(silence)) => {
+            assert!(silence > Duration::from_secs(10));
+        }
+        result => panic!("expected a heartbeat timeout, got {:?}", result),
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn dropped_socket_resumes() {
+    let url = start_server(vec![Behaviour::Drop, Behaviour::Serve]).await;
+    let (mut client, mut reconnects) = connect(&url).await;
+    client.set_reconnect_policy(ReconnectPolicy::new().initial_delay(Duration::from_millis(10)));
+    let cancel_handle = client.cancel_handle();
+    let run = tokio::spawn(async move { client.run().await });
+
+    assert!(!next_reconnect(&mut reconnects).await);
+    assert!(next_reconnect(&mut reconnects).await);
+    cancel_handle.cancel();
+
+    let reason = tokio::time::timeout(TIMEOUT, run)
+        .await
+        .expect("client exit")
+        .expect("join client")
+        .expect("run");
+    assert_eq!(reason, ExitReason::Cancelled);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn dropped_socket_rehandshakes() {
+    let url = start_server(vec![Behaviour::Drop, Behaviour::Forget]).await;
+    let (mut client, mut reconnects) = connect(&url).await;
+    client.set_reconnect_policy(ReconnectPolicy::new().initial_delay(Duration::from_millis(10)));
+    let cancel_handle = client.cancel_handle();
+    let run = tokio::spawn(async move { client.run().await });
+
+    assert!(!next_reconnect(&mut reconnects).await);
+    assert!(!next_reconnect(&mut reconnects).await);
+    cancel_handle.cancel();
+
+    let reason = tokio::time::timeout(TIMEOUT, run)
+        .await
+        .expect("client exit")
+        .expect("join client")
+        .expect("run");
+    assert_eq!(reason, ExitReason::Cancelled);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn dropped_socket_without_reconnect() {
+    let url = start_server(vec![Behaviour::Drop]).await;
+    let (mut client, mut reconnects) = connect(&url).await;
+    client.set_reconnect_policy(ReconnectPolicy::disabled());
+    let run = tokio::spawn(async move { client.run().await });
+
+    assert!(!next_reconnect(&mut reconnects).await);
+
+    // Depending on whether the client was reading or writing, the drop shows up as the end of the stream or an io error
+    let result = tokio::time::timeout(TIMEOUT, run)
+        .await
+        .expect("client exit")
+        .expect("join client");
+    match result {
+        Ok(ExitReason::StreamEnded) | Err(CometError::Ws(tungstenite::Error::Io(_))) => {}
+        result => panic!("expected the client to stop, got {:?}", result),
+    }
+    assert!(reconnects.try_recv().is_err());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn subscribe_rejected() {
+    let url = start_server(vec![Behaviour::Serve]).await;
+    let (mut client, mut reconnects) = connect(&url).await;
+    let ctx = client.context();
+    let cancel_handle = client.cancel_handle();
+    let run = tokio::spawn(async move { client.run().await });
+
+    assert!(!next_reconnect(&mut reconnects).await);
+
+    let packet = Packet::new()
+        .channel(Channel::Subscribe)
+        .client_id(ctx.get_client_id().expect("client id"))
+        .subscription(FORBIDDEN_CHANNEL.parse().expect("channel"));
+    let reply = ctx
+        .request(packet, Channel::Subscribe.as_str(), |_| true, TIMEOUT)
+        .await
+        .expect("subscribe reply");
+    assert_eq!(reply.successful, Some(false));
+    assert_eq!(reply.error.as_deref(), Some("403::Forbidden"));
+
+    // The rejection is counted as a server error, not a handler failure
+    tokio::time::timeout(TIMEOUT, async {
+        while ctx.metrics().transport_errors == 0 {
+            tokio::time::delay_for(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("transport error");
+    assert_eq!(ctx.metrics().handler_errors, 0);
+
+    // The rejection must not take the client down
+    cancel_handle.cancel();
+    let reason = tokio::time::timeout(TIMEOUT, run)
+        .await
+        .expect("client exit")
+        .expect("join client")
+        .expect("run");
+    assert_eq!(reason, ExitReason::Cancelled);
+}
+
+/// Publish a number on the echo channel
+fn echo_packet(ctx: &Context, n: u64) -> Packet {
+    Packet::new()
+        .channel(ECHO_CHANNEL.parse().expect("channel"))
+        .client_id(ctx.get_client_id().expect("client id"))
+        .data(cometd::json!({ "n": n }))
+}
+
+fn echo_number(packet: &Packet) -> Option<u64> {
+    packet.data.as_ref()?.get("n")?.as_u64()
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn wait_for_match() {
+    let url = start_server(vec![Behaviour::Serve]).await;
+    let (mut client, mut reconnects) = connect(&url).await;
+    let ctx = client.context();
+    tokio::spawn(async move { client.run().await });
+
+    assert!(!next_reconnect(&mut reconnects).await);
+
+    // The wait is polled first, so it is registered before anything is published
+    let (packet, published) = tokio::join!(
+        ctx.wait_for(
+            ECHO_CHANNEL,
+            |packet| echo_number(packet) == Some(2),
+            TIMEOUT
+        ),
+        async {
+            ctx.send_packet(echo_packet(&ctx, 1)).await?;
+            ctx.send_packet(echo_packet(&ctx, 2)).await
+        },
+    );
+    published.expect("publish");
+    assert_eq!(echo_number(&packet.expect("matching packet")), Some(2));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn wait_for_timeout() {
+    let url = start_server(vec![Behaviour::Serve]).await;
+    let (mut client, mut reconnects) = connect(&url).await;
+    let ctx = client.context();
+    tokio::spawn(async move { client.run().await });
+
+    assert!(!next_reconnect(&mut reconnects).await);
+
+    // The predicate holds a reference, which is released when its waiter is dropped
+    let token = Arc::new(());
+    let held = token.clone();
+    let timeout = Duration::from_millis(200);
+    let (result, published) = tokio::join!(
+        ctx.wait_for(
+            ECHO_CHANNEL,
+            move |_| {
+                let _ = &held;
+                false
+            },
+            timeout,
+        ),
+        ctx.send_packet(echo_packet(&ctx, 1)),
+    );
+    published.expect("publish");
+
+    match result {
+        Err(CometError::Timeout(t)) => assert_eq!(t, timeout),
+        result => panic!("expected a timeout, got {:?}", result),
+    }
+    assert_eq!(Arc::strong_count(&token), 1);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn request_replies() {
+    let url = start_server(vec![Behaviour::Serve]).await;
+    let (mut client, mut reconnects) = connect(&url).await;
+    let ctx = client.context();
+    tokio::spawn(async move { client.run().await });
+
+    assert!(!next_reconnect(&mut reconnects).await);
+
+    // The server replies at once, so some replies are dispatched before the send returns.
+    // Every one of them must still reach its request.
+    for n in 0..50 {
+        let reply = ctx
+            .request(
+                echo_packet(&ctx, n),
+                ECHO_CHANNEL,
+                move |packet| echo_number(packet) == Some(n),
+                TIMEOUT,
+            )
+            .await
+            .expect("reply");
+        assert_eq!(echo_number(&reply), Some(n));
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn waiters_fail_on_exit() {
+    let url = start_server(vec![Behaviour::Serve]).await;
+    let (mut client, mut reconnects) = connect(&url).await;
+    let ctx = client.context();
+    let cancel_handle = client.cancel_handle();
+    let run = tokio::spawn(async move { client.run().await });
+
+    assert!(!next_reconnect(&mut reconnects).await);
+
+    let token = Arc::new(());
+    let held = token.clone();
+    let (result, ()) = tokio::join!(
+        ctx.wait_for(
+            ECHO_CHANNEL,
+            move |_| {
+                let _ = &held;
+                false
+            },
+            TIMEOUT,
+        ),
+        async { cancel_handle.cancel() },
+    );
+
+    match result {
+        Err(CometError::ClientExited) => {}
+        result => panic!("expected the wait to fail, got {:?}", result),
+    }
+    assert_eq!(Arc::strong_count(&token), 1);
+
+    let reason = tokio::time::timeout(TIMEOUT, run)
+        .await
+        .expect("client exit")
+        .expect("join client")
+        .expect("run");
+    assert_eq!(reason, ExitReason::Cancelled);
+}
@@ -0,0 +1,37 @@
+use cometd::metrics::{
+    ChannelMetrics,
+    MetricsSnapshot,
+};
+use std::time::Duration;
+
+#[test]
+fn prometheus_text() {
+    let mut snapshot = MetricsSnapshot::default();
+    snapshot.channels.insert(
+        "/service/\"quoted\"".to_string(),
+        ChannelMetrics {
+            packets_in: 2,
+            packets_out: 1,
+            bytes_in: 300,
+            bytes_out: 120,
+        },
+    );
+    snapshot.handshakes = 2;
+    snapshot.rehandshakes = 1;
+    snapshot.connect_latency_sum = Duration::from_millis(1500);
+    snapshot.connect_latency_count = 3;
+    snapshot.queued_packets = 4;
+    snapshot.transport_errors = 5;
+
+    let text = snapshot.to_prometheus();
+
+    assert!(text.contains("cometd_packets_in_total{channel=\"/service/\\\"quoted\\\"\"} 2\n"));
+    assert!(text.contains("cometd_bytes_out_total{channel=\"/service/\\\"quoted\\\"\"} 120\n"));
+    assert!(text.contains("# TYPE cometd_handshakes_total counter\ncometd_handshakes_total 2\n"));
+    assert!(text.contains("cometd_rehandshakes_total 1\n"));
+    assert!(text.contains("cometd_connect_latency_seconds_sum 1.5\n"));
+    assert!(text.contains("cometd_connect_latency_seconds_count 3\n"));
+    assert!(text.contains("cometd_queued_packets 4\n"));
+    assert!(text.contains("cometd_handler_errors_total 0\n"));
+    assert!(text.contains("cometd_transport_errors_total 5\n"));
+}
@@ -0,0 +1,294 @@
+use cometd::packet::{
+    decode_frame,
+    Advice,
+    Channel,
+    ConnectionType,
+    InvalidChannelError,
+    Packet,
+    Reconnect,
+};
+use proptest::prelude::*;
+use std::collections::HashMap;
+
+const PACKET_FIELDS: &[&str] = &[
+    "advice",
+    "channel",
+    "clientId",
+    "connectionType",
+    "data",
+    "error",
+    "ext",
+    "id",
+    "minimumVersion",
+    "subscription",
+    "successful",
+    "supportedConnectionTypes",
+    "version",
+];
+
+const ADVICE_FIELDS: &[&str] = &["timeout", "reconnect", "interval", "maxInterval"];
+
+fn channel() -> impl Strategy<Value = Channel> {
+    prop_oneof![
+        Just(Channel::Handshake),
+        Just(Channel::Connect),
+        Just(Channel::Subscribe),
+        Just(Channel::Unsubscribe),
+        Just(Channel::Disconnect),
+        ".*".prop_map(Channel::from_string),
+    ]
+}
+
+fn connection_type() -> impl Strategy<Value = ConnectionType> {
+    prop_oneof![
+        Just(ConnectionType::WebSocket),
+        Just(ConnectionType::LongPolling),
+        Just(ConnectionType::CallbackPolling),
+        ".*".prop_map(ConnectionType::from_string),
+    ]
+}
+
+fn reconnect() -> impl Strategy<Value = Reconnect> {
+    prop_oneof![
+        Just(Reconnect::Retry),
+        Just(Reconnect::Handshake),
+        Just(Reconnect::None),
+    ]
+}
+
+fn json_value() -> impl Strategy<Value = serde_json::Value> {
+    let leaf = prop_oneof![
+        Just(serde_json::Value::Null),
+        any::<bool>().prop_map(serde_json::Value::from),
+        any::<i64>().prop_map(serde_json::Value::from),
+        any::<u64>().prop_map(serde_json::Value::from),
+        ".*".prop_map(serde_json::Value::from),
+    ];
+
+    leaf.prop_recursive(3, 16, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(serde_json::Value::from),
+            prop::collection::hash_map(".*", inner, 0..4)
+                .prop_map(|map| serde_json::Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+/// `null` is indistinguishable from a missing optional field, so it is left out
+fn json_field() -> impl Strategy<Value = serde_json::Value> {
+    json_value().prop_filter("null field", |value| !value.is_null())
+}
+
+fn extra(
+    known: &'static [&'static str],
+) -> impl Strategy<Value = HashMap<String, serde_json::Value>> {
+    prop::collection::hash_map(
+        ".*".prop_filter("known field", move |key| !known.contains(&key.as_str())),
+        json_value(),
+        0..4,
+    )
+}
+
+fn advice() -> impl Strategy<Value = Advice> {
+    (
+        any::<Option<u64>>(),
+        proptest::option::of(reconnect()),
+        any::<Option<i64>>(),
+        any::<Option<i64>>(),
+        extra(ADVICE_FIELDS),
+    )
+        .prop_map(
+            |(timeout, reconnect, interval, max_interval, extra)| Advice {
+                timeout,
+                reconnect,
+                interval,
+                max_interval,
+                extra,
+            },
+        )
+}
+
+fn packet() -> impl Strategy<Value = Packet> {
+    (
+        (
+            proptest::option::of(advice()),
+            channel(),
+            any::<Option<String>>(),
+            proptest::option::of(connection_type()),
+            proptest::option::of(json_field()),
+            any::<Option<String>>(),
+            proptest::option::of(json_field()),
+        ),
+        (
+            any::<Option<String>>(),
+            any::<Option<String>>(),
+            proptest::option::of(channel()),
+            any::<Option<bool>>(),
+            proptest::option::of(prop::collection::vec(connection_type(), 0..4)),
+            any::<Option<String>>(),
+            extra(PACKET_FIELDS),
+        ),
+    )
+        .prop_map(
+            |(
+                (advice, channel, client_id, connection_type, data, error, ext),
+                (
+                    id,
+                    minimum_version,
+                    subscription,
+                    successful,
+                    supported_connection_types,
+                    version,
+                    extra,
+                ),
+            )| Packet {
+                advice,
+                channel,
+                client_id,
+                connection_type,
+                data,
+                error,
+                ext,
+                id,
+                minimum_version,
+                subscription,
+                successful,
+                supported_connection_types,
+                version,
+                extra,
+            },
+        )
+}
+
+proptest! {
+    #[test]
+    fn channel_string_round_trip(channel in channel()) {
+        prop_assert_eq!(Channel::from(channel.as_str()), channel.clone());
+        prop_assert_eq!(Channel::from(channel.clone().into_string()), channel);
+    }
+
+    #[test]
+    fn channel_serde_round_trip(channel in channel()) {
+        let json = serde_json::to_string(&channel).unwrap();
+        prop_assert_eq!(serde_json::from_str::<Channel>(&json).unwrap(), channel);
+    }
+
+    #[test]
+    fn connection_type_round_trip(connection_type in connection_type()) {
+        let json = serde_json::to_string(&connection_type).unwrap();
+        prop_assert_eq!(serde_json::from_str::<ConnectionType>(&json).unwrap(), connection_type.clone());
+        prop_assert_eq!(ConnectionType::from(connection_type.clone().into_string()), connection_type);
+    }
+
+    #[test]
+    fn advice_round_trip(advice in advice()) {
+        let json = serde_json::to_string(&advice).unwrap();
+        prop_assert_eq!(serde_json::from_str::<Advice>(&json).unwrap(), advice);
+    }
+
+    #[test]
+    fn packet_round_trip(packet in packet()) {
+        let json = serde_json::to_string(&packet).unwrap();
+        prop_assert_eq!(serde_json::from_str::<Packet>(&json).unwrap(), packet);
+    }
+
+    #[test]
+    fn frame_round_trip(packets in prop::collection::vec(packet(), 0..4)) {
+        let json = serde_json::to_string(&packets).unwrap();
+        prop_assert_eq!(decode_frame(&json).unwrap(), packets);
+    }
+
+    #[test]
+    fn single_packet_frame(packet in packet()) {
+        let json = serde_json::to_string(&packet).unwrap();
+        prop_assert_eq!(decode_frame(&json).unwrap(), vec![packet]);
+    }
+
+    #[test]
+    fn valid_channel_names_parse(name in "(/[a-zA-Z0-9_!~()$@-]{1,8}){1,4}(/\\*\\*?)?") {
+        let channel: Channel = name.parse().unwrap();
+        prop_assert_eq!(channel.as_str(), name.as_str());
+        prop_assert_eq!(channel.segments().collect::<Vec<_>>().join("/"), &name[1..]);
+    }
+
+    #[test]
+    fn decode_frame_does_not_panic(frame in ".*") {
+        let _ = decode_frame(&frame);
+    }
+}
+
+#[test]
+fn decode_single_object_frame() {
+    let frame = r#" {"channel":"/meta/connect","successful":true} "#;
+    let packets = decode_frame(frame).unwrap();
+
+    assert_eq!(packets.len(), 1);
+    assert_eq!(packets[0].channel, Channel::Connect);
+    assert_eq!(packets[0].successful, Some(true));
+}
+
+#[test]
+fn decode_escaped_channel() {
+    let frame = r#"[{"channel":"\/service\/player"}]"#;
+    let packets = decode_frame(frame).unwrap();
+
+    assert_eq!(packets[0].channel, Channel::from("/service/player"));
+}
+
+#[test]
+fn invalid_channel_names() {
+    let cases = [
+        ("", InvalidChannelError::MissingLeadingSlash),
+        ("service/player", InvalidChannelError::MissingLeadingSlash),
+        ("/", InvalidChannelError::EmptySegment),
+        ("/chat//demo", InvalidChannelError::EmptySegment),
+        ("/chat/", InvalidChannelError::EmptySegment),
+        ("/chat demo", InvalidChannelError::InvalidCharacter(' ')),
+        ("/chat/*/demo", InvalidChannelError::MisplacedWildcard),
+        ("/chat/***", InvalidChannelError::MisplacedWildcard),
+        ("/chat/d*", InvalidChannelError::MisplacedWildcard),
+    ];
+
+    for (name, error) in cases.iter() {
+        assert_eq!(name.parse::<Channel>().unwrap_err(), *error, "{}", name);
+    }
+}
+
+#[test]
+fn classify_channels() {
+    let handshake: Channel = "/meta/handshake".parse().unwrap();
+    assert_eq!(handshake, Channel::Handshake);
+    assert!(handshake.is_meta());
+    assert!(!handshake.is_broadcast());
+
+    assert_eq!(Channel::from("/meta/unsubscribe"), Channel::Unsubscribe);
+    assert_eq!(Channel::from("/meta/disconnect"), Channel::Disconnect);
+
+    let player: Channel = "/service/player".parse().unwrap();
+    assert!(player.is_service());
+    assert!(!player.is_meta());
+    assert!(!player.is_broadcast());
+    assert!(!player.is_wildcard());
+
+    let chat: Channel = "/chat/**".parse().unwrap();
+    assert!(chat.is_broadcast());
+    assert!(chat.is_wildcard());
+    assert_eq!(chat.segments().collect::<Vec<_>>(), ["chat", "**"]);
+}
+
+#[test]
+fn match_channel_patterns() {
+    let channel = |s: &str| s.parse::<Channel>().unwrap();
+
+    assert!(channel("/chat/**").matches(&channel("/chat/room")));
+    assert!(channel("/chat/**").matches(&channel("/chat/room/1")));
+    assert!(!channel("/chat/**").matches(&channel("/chat")));
+
+    assert!(channel("/chat/*").matches(&channel("/chat/room")));
+    assert!(!channel("/chat/*").matches(&channel("/chat/room/1")));
+    assert!(!channel("/chat/*").matches(&channel("/news/room")));
+
+    assert!(channel("/service/player").matches(&channel("/service/player")));
+    assert!(!channel("/service/player").matches(&channel("/service/player/1")));
+    assert!(!channel("/service/player").matches(&channel("/service")));
+}
@@ -0,0 +1,46 @@
+use cometd::ReconnectPolicy;
+use std::time::Duration;
+
+#[test]
+fn backoff_grows() {
+    let policy = ReconnectPolicy::new()
+        .initial_delay(Duration::from_millis(100))
+        .multiplier(3.0)
+        .max_delay(Duration::from_secs(60));
+
+    assert_eq!(policy.delay(0), Duration::from_millis(100));
+    assert_eq!(policy.delay(1), Duration::from_millis(100));
+    assert_eq!(policy.delay(2), Duration::from_millis(300));
+    assert_eq!(policy.delay(3), Duration::from_millis(900));
+}
+
+#[test]
+fn backoff_clamped() {
+    let policy = ReconnectPolicy::new()
+        .initial_delay(Duration::from_secs(1))
+        .multiplier(2.0)
+        .max_delay(Duration::from_secs(5));
+
+    assert_eq!(policy.delay(3), Duration::from_secs(4));
+    assert_eq!(policy.delay(4), Duration::from_secs(5));
+    assert_eq!(policy.delay(100), Duration::from_secs(5));
+    assert_eq!(policy.delay(u32::MAX), Duration::from_secs(5));
+}
+
+#[test]
+fn max_attempts() {
+    let policy = ReconnectPolicy::new().max_attempts(2);
+    assert!(policy.should_retry(0));
+    assert!(policy.should_retry(1));
+    assert!(!policy.should_retry(2));
+
+    let forever = ReconnectPolicy::default();
+    assert!(forever.should_retry(0));
+    assert!(forever.should_retry(u32::MAX));
+}
+
+#[test]
+fn disabled() {
+    let policy = ReconnectPolicy::disabled();
+    assert!(!policy.should_retry(0));
+}
@@ -31,12 +31,26 @@ async fn main() {
         println!("Testing code {}..", code);
 
         let code_str = format!("{:06}", code); // Min length code: ?
-        let challenge = client.get_token(&code_str).await;
+        let probe = client.probe_code(&code_str).await;
 
-        match challenge {
-            Ok(c) => {
+        match probe {
+            Ok(probe) => {
                 println!("Located potential kahoot: {}", code_str);
-                println!("Decoded Challenge: {}", c);
+
+                let info = probe.session_info();
+                println!(
+                    "Game mode: {:?}, team mode: {}, namerator: {}, two factor auth: {}",
+                    info.game_mode,
+                    info.team_mode(),
+                    info.namerator,
+                    info.two_factor_auth
+                );
+
+                match client.solve(probe).await {
+                    Ok(c) => println!("Decoded Challenge: {}", c),
+                    Err(e) => eprintln!("Failed to decode challenge: {}", e),
+                }
+
                 println!("Testing Session validity...");
 
                 let client_connect = kahoot::Client::connect_with_handler(
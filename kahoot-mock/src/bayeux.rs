@@ -0,0 +1,283 @@
+use crate::{
+    challenge::random_hex,
+    host::{
+        player_message,
+        Game,
+    },
+    State,
+};
+use cometd::packet::{
+    decode_frame,
+    Advice,
+    Channel,
+    ConnectionType,
+    Packet,
+    Reconnect,
+};
+use futures::{
+    SinkExt,
+    StreamExt,
+};
+use serde_json::json;
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    net::TcpStream,
+    sync::mpsc,
+};
+use tungstenite::{
+    handshake::server::{
+        Request,
+        Response,
+    },
+    Message as TMessage,
+};
+
+const COMETD_PREFIX: &str = "/cometd/";
+const CONTROLLER_CHANNEL: &str = "/service/controller";
+
+/// The id of the message a player submits their team members with
+const TEAM_MEMBERS_ID: u64 = 18;
+
+/// How long a `/meta/connect` is held open before it is answered
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serve a websocket connection to `/cometd/{code}/{token}`
+#[allow(clippy::result_large_err)] // The handshake callback's error type is set by tungstenite
+pub(crate) async fn handle(state: Arc<State>, stream: TcpStream) {
+    let mut path = None;
+    let ws = tokio_tungstenite::accept_hdr_async(stream, |req: &Request, res: Response| {
+        path = Some(req.uri().path().to_string());
+        Ok(res)
+    })
+    .await;
+
+    let mut ws = match ws {
+        Ok(ws) => ws,
+        Err(_) => return,
+    };
+
+    let game = match path.as_deref().and_then(|path| find_game(&state, path)) {
+        Some(game) => game,
+        None => {
+            let _ = ws.close(None).await;
+            return;
+        }
+    };
+
+    let (mut ws_tx, mut ws_rx) = ws.split();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Packet>();
+
+    // Batch whatever is queued into a single frame
+    tokio::spawn(async move {
+        while let Some(packet) = rx.recv().await {
+            let mut packets = vec![packet];
+            while let Ok(packet) = rx.try_recv() {
+                packets.push(packet);
+            }
+
+            let frame = match serde_json::to_string(&packets) {
+                Ok(frame) => frame,
+                Err(_) => continue,
+            };
+
+            if ws_tx.send(TMessage::Text(frame)).await.is_err() {
+                break;
+            }
+        }
+
+        let _ = ws_tx.close().await;
+    });
+
+    let mut session = Session {
+        game,
+        client_id: random_hex(32),
+        tx,
+        connected: false,
+    };
+
+    while let Some(Ok(msg)) = ws_rx.next().await {
+        let frame = match msg {
+            TMessage::Text(frame) => frame,
+            TMessage::Close(_) => break,
+            _ => continue,
+        };
+
+        let packets = match decode_frame(&frame) {
+            Ok(packets) => packets,
+            Err(_) => break,
+        };
+
+        for packet in packets {
+            if !session.handle_packet(packet) {
+                return;
+            }
+        }
+    }
+}
+
+/// Find the game a cometd path belongs to, checking its session token
+fn find_game(state: &State, path: &str) -> Option<Arc<Game>> {
+    let mut parts = path.strip_prefix(COMETD_PREFIX)?.split('/');
+    let code = parts.next()?;
+    let token = parts.next()?;
+
+    let game = state.games.lock().unwrap().get(code).cloned()?;
+    if !game.state.lock().unwrap().tokens.contains(token) {
+        return None;
+    }
+
+    Some(game)
+}
+
+/// A connected cometd client
+struct Session {
+    game: Arc<Game>,
+    client_id: String,
+    tx: mpsc::UnboundedSender<Packet>,
+
+    /// Whether the first connect was answered
+    connected: bool,
+}
+
+impl Session {
+    /// Handle a packet from the client. Returns false once the client disconnected.
+    fn handle_packet(&mut self, packet: Packet) -> bool {
+        let reply = Packet::new()
+            .channel(packet.channel.clone())
+            .client_id(self.client_id.clone());
+        let reply = match packet.id.clone() {
+            Some(id) => reply.id(id),
+            None => reply,
+        };
+
+        match packet.channel {
+            Channel::Handshake => {
+                self.send(
+                    reply
+                        .successful(true)
+                        .version("1.0".to_string())
+                        .supported_connection_types(vec![ConnectionType::WebSocket])
+                        .advice(
+                            Advice::new()
+                                .reconnect(Reconnect::Retry)
+                                .timeout(CONNECT_TIMEOUT.as_millis() as u64)
+                                .interval(0),
+                        ),
+                );
+            }
+            Channel::Connect if !self.connected => {
+                // Like a real server, answer the first connect straight away
+                self.connected = true;
+                self.send(reply.successful(true));
+            }
+            Channel::Connect => {
+                // and hold later ones open
+                let tx = self.tx.clone();
+                tokio::spawn(async move {
+                    tokio::time::delay_for(CONNECT_TIMEOUT).await;
+                    let _ = tx.send(reply.successful(true));
+                });
+            }
+            Channel::Subscribe | Channel::Unsubscribe => {
+                let reply = match packet.subscription {
+                    Some(subscription) => reply.subscription(subscription),
+                    None => reply,
+                };
+
+                self.send(reply.successful(true));
+            }
+            Channel::Disconnect => {
+                self.send(reply.successful(true));
+                return false;
+            }
+            Channel::Other(ref channel) if channel == CONTROLLER_CHANNEL => {
+                if let Some(data) = packet.data.as_ref() {
+                    self.handle_controller(data);
+                }
+            }
+            Channel::Other(_) => {}
+        }
+
+        true
+    }
+
+    fn handle_controller(&self, data: &serde_json::Value) {
+        self.game
+            .state
+            .lock()
+            .unwrap()
+            .controller_messages
+            .push(data.clone());
+
+        match data.get("type").and_then(|t| t.as_str()) {
+            Some("login") if self.game.state.lock().unwrap().ignore_logins => {}
+            Some("login") => {
+                let name = data
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or_default();
+                match self.game.login(name, &self.client_id, self.tx.clone()) {
+                    Ok(player) => {
+                        let team_mode = self.game.state.lock().unwrap().team_mode;
+                        let mut response = json!({ "type": "loginResponse", "cid": player.cid });
+                        if team_mode {
+                            response["gameMode"] = json!("team");
+                        }
+                        self.send_controller(response);
+                        self.send(player_message(
+                            14,
+                            &json!({
+                                "playerName": player.name,
+                                "quizType": "quiz",
+                                "playerV2": true,
+                                "hostPrimaryUsage": "teacher",
+                            }),
+                            &player.cid,
+                        ));
+                    }
+                    Err(description) => {
+                        self.send_controller(json!({
+                            "type": "loginResponse",
+                            "error": "USER_INPUT",
+                            "description": description,
+                        }));
+                    }
+                }
+            }
+            Some("message") => {
+                let content = data
+                    .get("content")
+                    .and_then(|c| c.as_str())
+                    .and_then(|c| serde_json::from_str::<serde_json::Value>(c).ok());
+
+                match data.get("id").and_then(|id| id.as_u64()) {
+                    Some(TEAM_MEMBERS_ID) => {
+                        let members =
+                            content.and_then(|c| serde_json::from_value::<Vec<String>>(c).ok());
+                        if let Some(members) = members {
+                            self.game.set_team(&self.client_id, members);
+                        }
+                    }
+                    _ => {
+                        let choice = content.and_then(|c| c.get("choice")?.as_u64());
+                        if let Some(choice) = choice {
+                            self.game.answer(&self.client_id, choice as usize);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn send_controller(&self, data: serde_json::Value) {
+        self.send(Packet::new().channel(CONTROLLER_CHANNEL.into()).data(data));
+    }
+
+    fn send(&self, packet: Packet) {
+        let _ = self.tx.send(packet);
+    }
+}
@@ -0,0 +1,57 @@
+use rand::{
+    distributions::Alphanumeric,
+    Rng,
+};
+
+/// A challenge in the shape kahoot sends, with its solution
+pub(crate) struct Challenge {
+    /// The challenge js
+    pub js: String,
+
+    /// The session token, xored with the challenge solution and base64 encoded
+    pub encoded_token: String,
+}
+
+impl Challenge {
+    /// Make a random challenge that hides the given session token
+    pub fn new(session_token: &str) -> Self {
+        let mut rng = rand::thread_rng();
+        let message: String = (&mut rng).sample_iter(Alphanumeric).take(100).collect();
+        let (a, b, c) = (
+            rng.gen_range(1, 100),
+            rng.gen_range(1, 100),
+            rng.gen_range(1, 100),
+        );
+        let offset: u64 = a * b + c;
+
+        let js = format!(
+            "decode.call(this, '{}'); function decode(message) {{var offset = {} * {} + {}; if(this.angular.isDate(offset)) console.log(\"Offset derived as: {{\", offset, \"}}\"); return _.replace(message,/./g, function(char, position) {{return String.fromCharCode((((char.charCodeAt(0)*position)+ offset ) % 77) + 48);}});}}",
+            message, a, b, c
+        );
+
+        let solution: Vec<u8> = message
+            .bytes()
+            .enumerate()
+            .map(|(position, c)| (((u64::from(c) * position as u64 + offset) % 77) + 48) as u8)
+            .collect();
+
+        let encoded: Vec<u8> = session_token
+            .bytes()
+            .zip(solution.iter().cycle())
+            .map(|(byte, key)| byte ^ key)
+            .collect();
+
+        Self {
+            js,
+            encoded_token: base64::encode(encoded),
+        }
+    }
+}
+
+/// Make a random hex string
+pub(crate) fn random_hex(len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| std::char::from_digit(rng.gen_range(0, 16), 16).unwrap())
+        .collect()
+}
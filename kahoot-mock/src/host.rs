@@ -0,0 +1,501 @@
+use cometd::packet::Packet;
+use rand::seq::SliceRandom;
+use serde_json::json;
+use std::{
+    collections::HashSet,
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+pub(crate) const PLAYER_CHANNEL: &str = "/service/player";
+
+/// How often `Host::wait_*` checks the game
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Points awarded for a correct answer
+const POINTS_PER_QUESTION: u64 = 1000;
+
+/// The first halves of the names a namerator game gives players
+const NAMERATOR_ADJECTIVES: &[&str] = &[
+    "Amber", "Cobalt", "Crimson", "Golden", "Jade", "Scarlet", "Silver", "Violet",
+];
+
+/// The second halves of the names a namerator game gives players
+const NAMERATOR_NOUNS: &[&str] = &[
+    "Comet", "Galaxy", "Meteor", "Nebula", "Orbit", "Pulsar", "Quasar", "Rocket",
+];
+
+/// Pick a name for a player joining a namerator game, one no other player has
+fn namerator_name(players: &[PlayerState], cid: &str) -> String {
+    let mut rng = rand::thread_rng();
+    let adjective = NAMERATOR_ADJECTIVES.choose(&mut rng).expect("adjectives");
+    let noun = NAMERATOR_NOUNS.choose(&mut rng).expect("nouns");
+    let name = format!("{}{}", adjective, noun);
+
+    if players.iter().any(|p| p.player.name == name) {
+        format!("{}{}", name, cid)
+    } else {
+        name
+    }
+}
+
+/// A quiz for the mock host to run
+#[derive(Debug, Clone, Default)]
+pub struct Quiz {
+    /// The name of the quiz
+    pub name: String,
+
+    /// The questions, in order
+    pub questions: Vec<Question>,
+}
+
+impl Quiz {
+    /// Make an empty quiz
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            questions: Vec::new(),
+        }
+    }
+
+    /// Add a question
+    pub fn question(mut self, question: Question) -> Self {
+        self.questions.push(question);
+        self
+    }
+
+    fn choice_counts(&self) -> Vec<usize> {
+        self.questions.iter().map(|q| q.choices).collect()
+    }
+}
+
+/// A multiple choice question
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Question {
+    /// The number of choices
+    pub choices: usize,
+
+    /// The index of the correct choice
+    pub correct: usize,
+}
+
+impl Question {
+    /// Make a question
+    pub fn new(choices: usize, correct: usize) -> Self {
+        Self { choices, correct }
+    }
+}
+
+/// A player that logged in to a mock game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Player {
+    /// The name the player joined with
+    pub name: String,
+
+    /// The id the game assigned to the player
+    pub cid: String,
+
+    /// The team member names the player submitted, in team mode
+    pub team: Option<Vec<String>>,
+}
+
+/// An answer a player submitted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmittedAnswer {
+    /// The name of the player
+    pub name: String,
+
+    /// The id of the player
+    pub cid: String,
+
+    /// The question that was active when the answer arrived
+    pub question_index: usize,
+
+    /// The submitted choice
+    pub choice: usize,
+}
+
+/// A mock game
+pub(crate) struct Game {
+    pub code: String,
+    pub quiz: Quiz,
+    pub state: Mutex<GameState>,
+}
+
+#[derive(Default)]
+pub(crate) struct GameState {
+    /// Session tokens issued for this game
+    pub tokens: HashSet<String>,
+
+    pub players: Vec<PlayerState>,
+    pub answers: Vec<SubmittedAnswer>,
+
+    /// The data of every packet players sent to the controller channel
+    pub controller_messages: Vec<serde_json::Value>,
+
+    /// The question being asked, if any
+    pub question: Option<usize>,
+
+    /// Whether the game names players itself
+    pub namerator: bool,
+
+    /// Whether players join in teams
+    pub team_mode: bool,
+
+    /// Whether logins are left unanswered
+    pub ignore_logins: bool,
+
+    next_cid: u64,
+}
+
+pub(crate) struct PlayerState {
+    pub player: Player,
+    pub client_id: String,
+    pub tx: UnboundedSender<Packet>,
+
+    pub score: u64,
+    pub correct: u64,
+    pub incorrect: u64,
+}
+
+impl Game {
+    pub fn new(code: &str, quiz: Quiz) -> Self {
+        Self {
+            code: code.to_string(),
+            quiz,
+            state: Mutex::new(GameState::default()),
+        }
+    }
+
+    /// Register a player, returning them, or why the name was rejected.
+    ///
+    /// Namerator games ignore the requested name and pick one for the player.
+    pub fn login(
+        &self,
+        name: &str,
+        client_id: &str,
+        tx: UnboundedSender<Packet>,
+    ) -> Result<Player, &'static str> {
+        let mut state = self.state.lock().unwrap();
+        if !state.namerator && state.players.iter().any(|p| p.player.name == name) {
+            return Err("Duplicate name");
+        }
+
+        state.next_cid += 1;
+        let cid = state.next_cid.to_string();
+        let name = if state.namerator {
+            namerator_name(&state.players, &cid)
+        } else {
+            name.to_string()
+        };
+
+        let player = Player {
+            name,
+            cid,
+            team: None,
+        };
+        state.players.push(PlayerState {
+            player: player.clone(),
+            client_id: client_id.to_string(),
+            tx,
+            score: 0,
+            correct: 0,
+            incorrect: 0,
+        });
+
+        Ok(player)
+    }
+
+    /// Record an answer from the player with the given client id
+    pub fn answer(&self, client_id: &str, choice: usize) {
+        let mut state = self.state.lock().unwrap();
+        let question_index = match state.question {
+            Some(question_index) => question_index,
+            None => return,
+        };
+
+        let player = match state.players.iter().find(|p| p.client_id == client_id) {
+            Some(player) => player.player.clone(),
+            None => return,
+        };
+
+        state.answers.push(SubmittedAnswer {
+            name: player.name,
+            cid: player.cid,
+            question_index,
+            choice,
+        });
+    }
+
+    /// Record the team members submitted by the player with the given client id
+    pub fn set_team(&self, client_id: &str, members: Vec<String>) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(player) = state.players.iter_mut().find(|p| p.client_id == client_id) {
+            player.player.team = Some(members);
+        }
+    }
+}
+
+/// Make a player message packet
+pub(crate) fn player_message(id: u64, content: &serde_json::Value, cid: &str) -> Packet {
+    Packet::new().channel(PLAYER_CHANNEL.into()).data(json!({
+        "type": "message",
+        "id": id,
+        "content": content.to_string(),
+        "cid": cid,
+    }))
+}
+
+/// Drives a mock game
+#[derive(Clone)]
+pub struct Host {
+    pub(crate) game: Arc<Game>,
+}
+
+impl Host {
+    /// The game code
+    pub fn code(&self) -> &str {
+        &self.game.code
+    }
+
+    /// The quiz being run
+    pub fn quiz(&self) -> &Quiz {
+        &self.game.quiz
+    }
+
+    /// Name players joining the game instead of letting them pick names
+    pub fn enable_namerator(&self) {
+        self.game.state.lock().unwrap().namerator = true;
+    }
+
+    /// Make players join in teams
+    pub fn enable_team_mode(&self) {
+        self.game.state.lock().unwrap().team_mode = true;
+    }
+
+    /// Never answer logins, so players time out joining
+    pub fn ignore_logins(&self) {
+        self.game.state.lock().unwrap().ignore_logins = true;
+    }
+
+    /// The players that logged in
+    pub fn players(&self) -> Vec<Player> {
+        let state = self.game.state.lock().unwrap();
+        state.players.iter().map(|p| p.player.clone()).collect()
+    }
+
+    /// Every answer submitted so far
+    pub fn answers(&self) -> Vec<SubmittedAnswer> {
+        self.game.state.lock().unwrap().answers.clone()
+    }
+
+    /// The data of every packet players sent to the controller channel, in the order it arrived
+    pub fn controller_messages(&self) -> Vec<serde_json::Value> {
+        self.game.state.lock().unwrap().controller_messages.clone()
+    }
+
+    /// Wait until at least `count` players logged in. Returns false on timeout.
+    pub async fn wait_for_players(&self, count: usize, timeout: Duration) -> bool {
+        self.wait_until(timeout, |state| state.players.len() >= count)
+            .await
+    }
+
+    /// Wait until at least `count` answers were submitted. Returns false on timeout.
+    pub async fn wait_for_answers(&self, count: usize, timeout: Duration) -> bool {
+        self.wait_until(timeout, |state| state.answers.len() >= count)
+            .await
+    }
+
+    async fn wait_until<F>(&self, timeout: Duration, predicate: F) -> bool
+    where
+        F: Fn(&GameState) -> bool,
+    {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if predicate(&self.game.state.lock().unwrap()) {
+                return true;
+            }
+
+            if Instant::now() > deadline {
+                return false;
+            }
+
+            tokio::time::delay_for(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Wait until a number of players submitted their teams
+    pub async fn wait_for_teams(&self, count: usize, timeout: Duration) -> bool {
+        self.wait_until(timeout, |state| {
+            state
+                .players
+                .iter()
+                .filter(|p| p.player.team.is_some())
+                .count()
+                >= count
+        })
+        .await
+    }
+
+    /// Send a message with the given id and content to every player
+    pub fn send_message(&self, id: u64, content: serde_json::Value) {
+        self.send_each(|_| Some((id, content.clone())));
+    }
+
+    /// Send a message to each player, built from that player's state
+    fn send_each<F>(&self, mut f: F)
+    where
+        F: FnMut(&PlayerState) -> Option<(u64, serde_json::Value)>,
+    {
+        let state = self.game.state.lock().unwrap();
+        for player in state.players.iter() {
+            if let Some((id, content)) = f(player) {
+                // Players that left just miss the message
+                let _ = player
+                    .tx
+                    .send(player_message(id, &content, &player.player.cid));
+            }
+        }
+    }
+
+    /// Send `StartQuiz`
+    pub fn start_quiz(&self) {
+        self.send_message(
+            9,
+            json!({
+                "quizName": self.game.quiz.name,
+                "quizType": "quiz",
+                "quizQuestionAnswers": self.game.quiz.choice_counts(),
+            }),
+        );
+    }
+
+    /// Send `GetReady` for a question
+    pub fn get_ready(&self, question_index: usize) {
+        self.send_message(
+            1,
+            json!({
+                "questionIndex": question_index,
+                "gameBlockType": "quiz",
+                "gameBlockLayout": "CLASSIC",
+                "quizQuestionAnswers": self.game.quiz.choice_counts(),
+                "timeLeft": 5,
+            }),
+        );
+    }
+
+    /// Send `StartQuestion` and start accepting answers for it
+    pub fn start_question(&self, question_index: usize) {
+        self.game.state.lock().unwrap().question = Some(question_index);
+        self.send_message(
+            2,
+            json!({
+                "questionIndex": question_index,
+                "gameBlockType": "quiz",
+                "quizQuestionAnswers": self.game.quiz.choice_counts(),
+            }),
+        );
+    }
+
+    /// Send `TimeUp` and stop accepting answers
+    pub fn time_up(&self) {
+        let question_index = self.game.state.lock().unwrap().question.take();
+        if let Some(question_index) = question_index {
+            self.send_message(4, json!({ "questionNumber": question_index }));
+        }
+    }
+
+    /// Score the last question and send each player their `RevealAnswer`
+    pub fn reveal_answer(&self, question_index: usize) {
+        let question = match self.game.quiz.questions.get(question_index) {
+            Some(question) => *question,
+            None => return,
+        };
+
+        let mut state = self.game.state.lock().unwrap();
+        let GameState {
+            players, answers, ..
+        } = &mut *state;
+
+        for player in players.iter_mut() {
+            let choice = answers
+                .iter()
+                .rev()
+                .find(|a| a.cid == player.player.cid && a.question_index == question_index)
+                .map(|a| a.choice);
+
+            let is_correct = choice == Some(question.correct);
+            let points = if is_correct { POINTS_PER_QUESTION } else { 0 };
+            player.score += points;
+            if is_correct {
+                player.correct += 1;
+            } else {
+                player.incorrect += 1;
+            }
+
+            let content = json!({
+                "type": "quiz",
+                "choice": choice.unwrap_or(0),
+                "isCorrect": is_correct,
+                "text": choice.map(|c| c.to_string()).unwrap_or_default(),
+                "receivedTime": 0,
+                "pointsQuestion": true,
+                "points": points,
+                "correctAnswers": [question.correct.to_string()],
+                "totalScore": player.score,
+                "pointsData": {
+                    "answerStreakPoints": {
+                        "streakLevel": 0,
+                        "streakBonus": 0,
+                        "totalStreakPoints": 0,
+                        "previousStreakLevel": 0,
+                        "previousStreakBonus": 0,
+                    },
+                    "questionPoints": points,
+                    "totalPointsWithBonuses": player.score,
+                    "totalPointsWithoutBonuses": player.score,
+                },
+                "rank": 0,
+                "nemesis": null,
+            });
+
+            let _ = player
+                .tx
+                .send(player_message(8, &content, &player.player.cid));
+        }
+    }
+
+    /// Send each player their `GameOver`
+    pub fn game_over(&self) {
+        let player_count = self.game.state.lock().unwrap().players.len();
+        self.send_each(|player| {
+            Some((
+                3,
+                json!({
+                    "rank": 0,
+                    "cid": player.player.cid,
+                    "correctCount": player.correct,
+                    "incorrectCount": player.incorrect,
+                    "isKicked": false,
+                    "isGhost": false,
+                    "unansweredCount": 0,
+                    "playerCount": player_count,
+                    "startTime": 0,
+                    "quizId": "mock",
+                    "name": player.player.name,
+                    "totalScore": player.score,
+                    "hostId": "mock",
+                    "challengeId": null,
+                    "isOnlyNonPointGameBlockKahoot": false,
+                }),
+            ))
+        });
+    }
+}
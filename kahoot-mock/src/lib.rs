@@ -0,0 +1,136 @@
+//! A local stand-in for the player-facing kahoot servers.
+//!
+//! `MockServer` serves session reservations over http and cometd over ws on localhost.
+//! Games are driven by a scriptable `Host`.
+
+mod bayeux;
+mod challenge;
+mod host;
+mod reserve;
+
+use crate::host::Game;
+pub use crate::host::{
+    Host,
+    Player,
+    Question,
+    Quiz,
+    SubmittedAnswer,
+};
+use hyper::service::{
+    make_service_fn,
+    service_fn,
+};
+use kahoot::Endpoints;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+use tokio::{
+    net::TcpListener,
+    sync::oneshot,
+};
+
+/// Mock server error
+#[derive(Debug, thiserror::Error)]
+pub enum MockError {
+    /// IO Error
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    /// Hyper HTTP Error
+    #[error("{0}")]
+    Hyper(#[from] hyper::Error),
+}
+
+/// State shared by the http and cometd servers
+#[derive(Default)]
+pub(crate) struct State {
+    games: Mutex<HashMap<String, Arc<Game>>>,
+}
+
+/// A mock kahoot server. It stops when dropped.
+pub struct MockServer {
+    state: Arc<State>,
+    reserve_addr: SocketAddr,
+    cometd_addr: SocketAddr,
+
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl MockServer {
+    /// Start a server on localhost, on random ports
+    pub async fn start() -> Result<Self, MockError> {
+        let state = Arc::new(State::default());
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let (http_shutdown_tx, http_shutdown_rx) = oneshot::channel::<()>();
+
+        let reserve_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = reserve_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| reserve::handle(state.clone(), req)))
+            }
+        });
+
+        let server =
+            hyper::Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))?.serve(make_service);
+        let reserve_addr = server.local_addr();
+        tokio::spawn(server.with_graceful_shutdown(async move {
+            let _ = http_shutdown_rx.await;
+        }));
+
+        let mut listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+        let cometd_addr = listener.local_addr()?;
+        let cometd_state = state.clone();
+        tokio::spawn(async move {
+            let mut shutdown_rx = shutdown_rx;
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _addr)) => {
+                            tokio::spawn(bayeux::handle(cometd_state.clone(), stream));
+                        }
+                        Err(_) => break,
+                    },
+                    _ = &mut shutdown_rx => break,
+                }
+            }
+
+            let _ = http_shutdown_tx.send(());
+        });
+
+        Ok(Self {
+            state,
+            reserve_addr,
+            cometd_addr,
+            _shutdown: shutdown_tx,
+        })
+    }
+
+    /// The endpoints a client should use to reach this server
+    pub fn endpoints(&self) -> Endpoints {
+        Endpoints::new()
+            .reserve_url(format!("http://{}/reserve/session", self.reserve_addr))
+            .cometd_url(format!("ws://{}/cometd", self.cometd_addr))
+            .host(self.reserve_addr.ip().to_string())
+    }
+
+    /// Open a game with the given code, returning its host.
+    ///
+    /// An existing game with the same code is replaced.
+    pub fn host_game(&self, code: &str, quiz: Quiz) -> Host {
+        let game = Arc::new(Game::new(code, quiz));
+        self.state
+            .games
+            .lock()
+            .unwrap()
+            .insert(code.to_string(), game.clone());
+
+        Host { game }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{
+    challenge::{
+        random_hex,
+        Challenge,
+    },
+    State,
+};
+use hyper::{
+    Body,
+    Request,
+    Response,
+    StatusCode,
+};
+use serde_json::json;
+use std::{
+    convert::Infallible,
+    sync::Arc,
+};
+
+const RESERVE_PREFIX: &str = "/reserve/session/";
+
+/// Serve `/reserve/session/{code}/`
+pub(crate) async fn handle(
+    state: Arc<State>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let code = req
+        .uri()
+        .path()
+        .strip_prefix(RESERVE_PREFIX)
+        .map(|code| code.trim_end_matches('/'));
+
+    let game = code.and_then(|code| state.games.lock().unwrap().get(code).cloned());
+    let game = match game {
+        Some(game) => game,
+        None => return Ok(status_response(StatusCode::NOT_FOUND)),
+    };
+
+    let session_token = random_hex(64);
+    let challenge = Challenge::new(&session_token);
+    let (namerator, team_mode) = {
+        let mut state = game.state.lock().unwrap();
+        state.tokens.insert(session_token);
+        (state.namerator, state.team_mode)
+    };
+
+    let body = json!({
+        "twoFactorAuth": false,
+        "namerator": namerator,
+        "participantId": null,
+        "smartPractice": false,
+        "gameMode": if team_mode { "team" } else { "classic" },
+        "challenge": challenge.js,
+    });
+
+    let response = Response::builder()
+        .header("x-kahoot-session-token", challenge.encoded_token)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap_or_else(|_| status_response(StatusCode::INTERNAL_SERVER_ERROR));
+
+    Ok(response)
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
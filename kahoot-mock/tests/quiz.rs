@@ -0,0 +1,422 @@
+use kahoot::{
+    challenge::NativeSolver,
+    message::{
+        GameOverMessage,
+        RevealAnswerMessage,
+        StartQuestionMessage,
+        StartQuizMessage,
+        TimeUpMessage,
+    },
+    Answer,
+    ClientBuilder,
+    Context,
+    ErrorPolicy,
+    Fallible,
+    KahootError,
+    KahootResult,
+    LoginRejection,
+};
+use kahoot_mock::{
+    MockServer,
+    Question,
+    Quiz,
+};
+use std::{
+    sync::atomic::{
+        AtomicU64,
+        Ordering,
+    },
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+const CODE: &str = "123456";
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Game events seen by a bot
+#[derive(Debug, PartialEq)]
+enum Event {
+    StartQuiz(String),
+    TimeUp,
+    RevealAnswer(bool),
+    /// The correct count and the tracked total score
+    GameOver(u64, u64),
+}
+
+struct AnswerBot {
+    events: mpsc::UnboundedSender<Event>,
+}
+
+impl AnswerBot {
+    fn new() -> (Self, mpsc::UnboundedReceiver<Event>) {
+        let (events, rx) = mpsc::unbounded_channel();
+        (Self { events }, rx)
+    }
+}
+
+#[kahoot::async_trait]
+impl kahoot::Handler for AnswerBot {
+    async fn on_start_quiz(&self, _ctx: Context, msg: StartQuizMessage) {
+        let _ = self.events.send(Event::StartQuiz(msg.quiz_name));
+    }
+
+    async fn on_start_question(&self, ctx: Context, _msg: StartQuestionMessage) {
+        ctx.submit(Answer::Quiz(1)).await.expect("submit answer");
+    }
+
+    async fn on_time_up(&self, _ctx: Context, _msg: TimeUpMessage) {
+        let _ = self.events.send(Event::TimeUp);
+    }
+
+    async fn on_reveal_answer(&self, _ctx: Context, msg: Box<RevealAnswerMessage>) {
+        let _ = self.events.send(Event::RevealAnswer(msg.is_correct));
+    }
+
+    async fn on_game_over(&self, ctx: Context, msg: Box<GameOverMessage>) {
+        let total_score = ctx.game_state().total_score;
+        let _ = self
+            .events
+            .send(Event::GameOver(msg.correct_count, total_score));
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn play_quiz() {
+    let server = MockServer::start().await.expect("start mock server");
+    let host = server.host_game(
+        CODE,
+        Quiz::new("Mock Quiz")
+            .question(Question::new(4, 1))
+            .question(Question::new(2, 0)),
+    );
+
+    let (bot, mut events) = AnswerBot::new();
+    let mut client = ClientBuilder::new(CODE.to_string(), "bot".to_string())
+        .challenge_client(kahoot::challenge::Client::with_solver(NativeSolver))
+        .endpoints(server.endpoints())
+        .relogin_delay(Duration::from_millis(600))
+        .lag(30)
+        .connect(bot)
+        .await
+        .expect("connect");
+    tokio::spawn(async move { client.run().await });
+
+    assert!(host.wait_for_players(1, TIMEOUT).await);
+    assert_eq!(host.players()[0].name, "bot");
+
+    host.start_quiz();
+    for question_index in 0..host.quiz().questions.len() {
+        host.get_ready(question_index);
+        host.start_question(question_index);
+        assert!(host.wait_for_answers(question_index + 1, TIMEOUT).await);
+        host.time_up();
+        host.reveal_answer(question_index);
+    }
+    host.game_over();
+
+    let answers = host.answers();
+    assert_eq!(answers.len(), 2);
+    assert_eq!(answers[0].question_index, 0);
+    assert_eq!(answers[1].question_index, 1);
+    assert!(answers.iter().all(|answer| answer.choice == 1));
+
+    // Handlers run in their own tasks, so events can be seen out of order
+    let mut seen = Vec::new();
+    while seen.len() < 6 {
+        let event = tokio::time::timeout(TIMEOUT, events.recv())
+            .await
+            .expect("game event")
+            .expect("open event channel");
+        seen.push(event);
+    }
+
+    assert_eq!(
+        seen.first(),
+        Some(&Event::StartQuiz("Mock Quiz".to_string()))
+    );
+    assert_eq!(seen.iter().filter(|e| **e == Event::TimeUp).count(), 2);
+    assert!(seen.contains(&Event::RevealAnswer(true)));
+    assert!(seen.contains(&Event::RevealAnswer(false)));
+    assert!(seen.contains(&Event::GameOver(1, 1000)));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn duplicate_name() {
+    let server = MockServer::start().await.expect("start mock server");
+    let host = server.host_game(CODE, Quiz::new("Mock Quiz"));
+
+    let connect = || {
+        let challenge_client =
+            kahoot::challenge::Client::with_solver(NativeSolver).endpoints(server.endpoints());
+        kahoot::Client::connect_with_challenge_client(
+            CODE.to_string(),
+            "bot".to_string(),
+            kahoot::DefaultHandler,
+            challenge_client,
+        )
+    };
+
+    let mut first = connect().await.expect("connect");
+    tokio::spawn(async move { first.run().await });
+    assert!(host.wait_for_players(1, TIMEOUT).await);
+
+    let mut second = connect().await.expect("connect");
+    match tokio::time::timeout(TIMEOUT, second.run()).await {
+        Ok(Err(kahoot::KahootError::InvalidLogin(
+            rejection @ LoginRejection::DuplicateName(_),
+        ))) => {
+            assert_eq!(rejection.description(), Some("Duplicate name"))
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(host.players().len(), 1);
+}
+
+/// Reports the assigned name along with the context's username
+struct NameratorBot {
+    assigned: mpsc::UnboundedSender<(String, String)>,
+}
+
+#[kahoot::async_trait]
+impl kahoot::Handler for NameratorBot {
+    async fn on_name_assigned(&self, ctx: Context, name: String) {
+        let _ = self.assigned.send((name, ctx.get_username().to_string()));
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn namerator() {
+    let server = MockServer::start().await.expect("start mock server");
+    let host = server.host_game(CODE, Quiz::new("Mock Quiz"));
+    host.enable_namerator();
+
+    let (assigned, mut assigned_rx) = mpsc::unbounded_channel();
+    let bot = NameratorBot { assigned };
+    let mut client = ClientBuilder::new(CODE.to_string(), "bot".to_string())
+        .challenge_client(kahoot::challenge::Client::with_solver(NativeSolver))
+        .endpoints(server.endpoints())
+        .connect(bot)
+        .await
+        .expect("connect");
+    tokio::spawn(async move { client.run().await });
+
+    let (name, username) = tokio::time::timeout(TIMEOUT, assigned_rx.recv())
+        .await
+        .expect("assigned name")
+        .expect("open name channel");
+    assert_ne!(name, "bot");
+    assert_eq!(username, name);
+    assert_eq!(host.players()[0].name, name);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn session_info() {
+    let server = MockServer::start().await.expect("start mock server");
+    let host = server.host_game(CODE, Quiz::new("Mock Quiz"));
+    host.enable_namerator();
+
+    let probe = kahoot::challenge::Client::new()
+        .endpoints(server.endpoints())
+        .probe_code(CODE)
+        .await
+        .expect("probe");
+    let info = probe.session_info();
+    assert!(info.namerator);
+    assert!(!info.two_factor_auth);
+    assert!(!info.team_mode());
+    assert_eq!(info.raw["challenge"], probe.challenge());
+}
+
+struct TeamBot;
+
+#[kahoot::async_trait]
+impl kahoot::Handler for TeamBot {
+    async fn on_team_members(&self, _ctx: Context, name: String) -> Vec<String> {
+        vec![name, "alice".to_string()]
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn team_mode() {
+    let server = MockServer::start().await.expect("start mock server");
+    let host = server.host_game(CODE, Quiz::new("Mock Quiz"));
+    host.enable_team_mode();
+
+    let probe = kahoot::challenge::Client::new()
+        .endpoints(server.endpoints())
+        .probe_code(CODE)
+        .await
+        .expect("probe");
+    assert!(probe.session_info().team_mode());
+
+    let mut client = ClientBuilder::new(CODE.to_string(), "bot".to_string())
+        .challenge_client(kahoot::challenge::Client::with_solver(NativeSolver))
+        .endpoints(server.endpoints())
+        .connect(TeamBot)
+        .await
+        .expect("connect");
+    tokio::spawn(async move { client.run().await });
+
+    assert!(host.wait_for_teams(1, TIMEOUT).await);
+    assert_eq!(
+        host.players()[0].team,
+        Some(vec!["bot".to_string(), "alice".to_string()])
+    );
+}
+
+struct FailingBot {
+    logins: mpsc::UnboundedSender<Context>,
+}
+
+#[kahoot::async_trait]
+impl kahoot::TryHandler for FailingBot {
+    async fn on_login(&self, ctx: Context) -> KahootResult<()> {
+        let _ = self.logins.send(ctx);
+        Err(KahootError::NoQuestion)
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn handler_errors_counted() {
+    let server = MockServer::start().await.expect("start mock server");
+    let _host = server.host_game(CODE, Quiz::new("Mock Quiz"));
+
+    let (logins, mut logins_rx) = mpsc::unbounded_channel();
+    let handler = Fallible::new(FailingBot { logins }).policy(ErrorPolicy::Log);
+    let mut client = ClientBuilder::new(CODE.to_string(), "bot".to_string())
+        .challenge_client(kahoot::challenge::Client::with_solver(NativeSolver))
+        .endpoints(server.endpoints())
+        .connect(handler)
+        .await
+        .expect("connect");
+    tokio::spawn(async move { client.run().await });
+
+    let ctx = tokio::time::timeout(TIMEOUT, logins_rx.recv())
+        .await
+        .expect("login")
+        .expect("open login channel");
+
+    // The error is handled after the callback returns
+    let counted = tokio::time::timeout(TIMEOUT, async {
+        while ctx.ctx.metrics().handler_errors == 0 {
+            tokio::time::delay_for(Duration::from_millis(10)).await;
+        }
+    })
+    .await;
+    assert!(counted.is_ok());
+
+    // Errors that stop the client are counted too
+    let (logins, mut logins_rx) = mpsc::unbounded_channel();
+    let handler = Fallible::new(FailingBot { logins }).policy(ErrorPolicy::Stop);
+    let mut client = ClientBuilder::new(CODE.to_string(), "stopping bot".to_string())
+        .challenge_client(kahoot::challenge::Client::with_solver(NativeSolver))
+        .endpoints(server.endpoints())
+        .connect(handler)
+        .await
+        .expect("connect");
+
+    match tokio::time::timeout(TIMEOUT, client.run()).await {
+        Ok(Err(KahootError::NoQuestion)) => {}
+        result => panic!("expected the handler error, got {:?}", result),
+    }
+    let ctx = logins_rx.try_recv().expect("login");
+    assert_eq!(ctx.ctx.metrics().handler_errors, 1);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn device_and_lag_sent() {
+    let server = MockServer::start().await.expect("start mock server");
+    let host = server.host_game(
+        CODE,
+        Quiz::new("Mock Quiz")
+            .question(Question::new(4, 1))
+            .question(Question::new(2, 0)),
+    );
+
+    let lag = AtomicU64::new(100);
+    let (bot, _events) = AnswerBot::new();
+    let mut client = ClientBuilder::new(CODE.to_string(), "bot".to_string())
+        .challenge_client(kahoot::challenge::Client::with_solver(NativeSolver))
+        .endpoints(server.endpoints())
+        .relogin_delay(Duration::from_millis(10))
+        .device(
+            kahoot::client::DeviceProfile::new()
+                .user_agent("mock agent".to_string())
+                .screen(800, 600),
+        )
+        .lag_source(move || lag.fetch_add(1, Ordering::SeqCst))
+        .connect(bot)
+        .await
+        .expect("connect");
+    tokio::spawn(async move { client.run().await });
+
+    assert!(host.wait_for_players(1, TIMEOUT).await);
+    host.start_quiz();
+    for question_index in 0..host.quiz().questions.len() {
+        host.start_question(question_index);
+        assert!(host.wait_for_answers(question_index + 1, TIMEOUT).await);
+    }
+
+    let expected_device = serde_json::json!({
+        "device": {
+            "userAgent": "mock agent",
+            "screen": { "width": 800, "height": 600 },
+        }
+    });
+    let parse = |s: &serde_json::Value| -> serde_json::Value {
+        serde_json::from_str(s.as_str().expect("string content")).expect("json content")
+    };
+
+    let messages = host.controller_messages();
+    let login = messages
+        .iter()
+        .find(|m| m["type"] == "login")
+        .expect("login packet");
+    assert_eq!(login["name"], "bot");
+    assert_eq!(parse(&login["content"]), expected_device);
+
+    let answers: Vec<_> = messages
+        .iter()
+        .filter(|m| m["type"] == "message" && m["id"] == 45)
+        .map(|m| parse(&m["content"]))
+        .collect();
+    assert_eq!(answers.len(), 2);
+    for (answer, expected_lag) in answers.iter().zip(100..) {
+        assert_eq!(answer["meta"]["lag"], expected_lag);
+        assert_eq!(parse(&answer["meta"]["device"]), expected_device);
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn login_timeout() {
+    let server = MockServer::start().await.expect("start mock server");
+    let host = server.host_game(CODE, Quiz::new("Mock Quiz"));
+    host.ignore_logins();
+
+    let join_timeout = Duration::from_secs(1);
+    let mut client = ClientBuilder::new(CODE.to_string(), "bot".to_string())
+        .challenge_client(kahoot::challenge::Client::with_solver(NativeSolver))
+        .endpoints(server.endpoints())
+        .relogin_delay(Duration::from_millis(10))
+        .join_timeout(join_timeout)
+        .connect(kahoot::DefaultHandler)
+        .await
+        .expect("connect");
+
+    match tokio::time::timeout(TIMEOUT, client.run()).await {
+        Ok(Err(KahootError::JoinTimeout(timeout))) => assert_eq!(timeout, join_timeout),
+        result => panic!("expected a join timeout, got {:?}", result),
+    }
+    assert!(host.players().is_empty());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn unknown_code() {
+    let server = MockServer::start().await.expect("start mock server");
+    let challenge_client = kahoot::challenge::Client::new().endpoints(server.endpoints());
+
+    match challenge_client.get_token(CODE).await {
+        Err(kahoot::KahootError::InvalidCode) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
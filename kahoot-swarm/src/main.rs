@@ -40,9 +40,13 @@ impl kahoot::Handler for BotHandler {
     async fn on_start_question(
         &self,
         ctx: kahoot::Context,
-        msg: kahoot::message::StartQuestionMessage,
+        _msg: kahoot::message::StartQuestionMessage,
     ) {
-        let choice = rand::thread_rng().gen_range(0, msg.quiz_question_answers[msg.question_index]);
+        let choices = match ctx.game_state().answer_count() {
+            Some(choices) => choices,
+            None => return self.on_error(ctx, kahoot::KahootError::NoQuestion).await,
+        };
+        let choice = rand::thread_rng().gen_range(0, choices);
         println!("Client {} submitting answer...", self.id);
         tokio::time::delay_for(std::time::Duration::from_millis(
             250 + ((self.id as f32 / 100.0) * 1000.0) as u64, // TODO: Can we go faster here?
@@ -75,6 +79,7 @@ pub enum TaskMessageData {
 pub struct Swarm {
     code: String,
     base_name: String,
+    challenge_client: kahoot::challenge::Client,
 
     task_tx: UnboundedSender<TaskMessage>,
     rx: Arc<Mutex<UnboundedReceiver<TaskMessage>>>,
@@ -83,11 +88,16 @@ pub struct Swarm {
 }
 
 impl Swarm {
-    pub fn new(code: String, base_name: String) -> Self {
+    pub fn new(
+        code: String,
+        base_name: String,
+        challenge_client: kahoot::challenge::Client,
+    ) -> Self {
         let (task_tx, rx) = tokio::sync::mpsc::unbounded_channel();
         Self {
             code,
             base_name,
+            challenge_client,
             task_tx,
             rx: Arc::new(Mutex::new(rx)),
             num_workers: Arc::new(AtomicU64::new(0)),
@@ -123,12 +133,11 @@ impl Swarm {
         let tx = self.task_tx.clone();
 
         let mut client = loop {
-            let res = kahoot::Client::connect_with_handler(
-                self.code.clone(),
-                format!("{}{}", self.base_name, id),
-                BotHandler { id, tx: tx.clone() },
-            )
-            .await;
+            let res =
+                kahoot::ClientBuilder::new(self.code.clone(), format!("{}{}", self.base_name, id))
+                    .challenge_client(self.challenge_client.clone())
+                    .connect(BotHandler { id, tx: tx.clone() })
+                    .await;
 
             match res {
                 Ok(client) => break client,
@@ -167,13 +176,12 @@ impl Swarm {
                             );
                         }
                         Err(e) => {
-                            if let kahoot::KahootError::InvalidLogin(invalid_login) = e {
-                                if invalid_login.description.as_deref() == Some("Duplicate name") {
-                                    println!("Worker #{} will not be restarted as it tried to log in with a duplicate name", msg.id);
-
-                                    // TODO: Proper shutdown
-                                    // break;
-                                }
+                            if let kahoot::KahootError::InvalidLogin(
+                                kahoot::LoginRejection::DuplicateName(_),
+                            ) = e
+                            {
+                                println!("Worker #{} will not be restarted as it tried to log in with a duplicate name", msg.id);
+                                continue;
                             }
 
                             let self_clone = self.clone();
@@ -238,7 +246,7 @@ async fn main() {
     let _ = stdout().flush();
     let base_name = read_line();
 
-    let swarm = Swarm::new(code, base_name);
+    let swarm = Swarm::new(code, base_name, challenge_client);
     let swarm1 = swarm.clone();
     tokio::spawn(async move {
         swarm1.add_n_workers(max_clients).await.unwrap();
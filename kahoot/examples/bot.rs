@@ -1,24 +1,42 @@
-use kahoot::Context;
+use kahoot::{
+    Context,
+    Fallible,
+    KahootResult,
+};
 use rand::Rng;
 use std::io::stdin;
 
 struct BotHandler;
 
 #[kahoot::async_trait]
-impl kahoot::Handler for BotHandler {
-    async fn on_login(&self, ctx: Context) {
+impl kahoot::TryHandler for BotHandler {
+    async fn on_login(&self, ctx: Context) -> KahootResult<()> {
         println!("Logged in as: {}", ctx.get_username());
+        Ok(())
     }
 
-    async fn on_get_ready(&self, _ctx: Context, msg: kahoot::message::GetReadyMessage) {
+    async fn on_get_ready(
+        &self,
+        _ctx: Context,
+        msg: kahoot::message::GetReadyMessage,
+    ) -> KahootResult<()> {
         dbg!(msg);
+        Ok(())
     }
 
-    async fn on_start_question(&self, ctx: Context, msg: kahoot::message::StartQuestionMessage) {
+    async fn on_start_question(
+        &self,
+        ctx: Context,
+        _msg: kahoot::message::StartQuestionMessage,
+    ) -> KahootResult<()> {
         tokio::time::delay_for(std::time::Duration::from_millis(250)).await; // Needed or kahoot thinks you were too fast
 
-        let choice = rand::thread_rng().gen_range(0, msg.quiz_question_answers[msg.question_index]);
-        ctx.submit_answer(choice).await.unwrap();
+        let choices = ctx
+            .game_state()
+            .answer_count()
+            .ok_or(kahoot::KahootError::NoQuestion)?;
+        let choice = rand::thread_rng().gen_range(0, choices);
+        ctx.submit_answer(choice).await
     }
 
     async fn on_error(&self, _ctx: Context, error: kahoot::KahootError) {
@@ -41,7 +59,9 @@ async fn main() {
     let name = read_line();
 
     let mut client =
-        match kahoot::Client::connect_with_handler(code.clone(), name, BotHandler).await {
+        match kahoot::Client::connect_with_handler(code.clone(), name, Fallible::new(BotHandler))
+            .await
+        {
             Ok(c) => c,
             Err(e) => {
                 eprintln!("Failed to connect to quiz '{}', got error: {:#?}", code, e);
@@ -53,6 +73,9 @@ async fn main() {
         Ok(_) => {
             println!("Client exited sucessfully");
         }
+        Err(kahoot::KahootError::InvalidLogin(reason)) => {
+            eprintln!("Login rejected: {}", reason);
+        }
         Err(e) => {
             eprintln!("Client exited with error: {:#?}", e);
         }
@@ -0,0 +1,148 @@
+//! Record a challenge from a live game into the regression corpus in `tests/challenges`.
+//!
+//! The recorded `expected` token is the one the live server accepted a cometd session with,
+//! so the corpus does not just repeat what the solvers produced.
+//!
+//! Usage: `cargo run --example record_challenge -- <code> [sample name]`
+
+use kahoot::{
+    async_trait,
+    challenge::{
+        decode_token,
+        default_solver,
+    },
+    Endpoints,
+};
+use std::{
+    path::Path,
+    sync::atomic::{
+        AtomicBool,
+        Ordering,
+    },
+    time::Duration,
+};
+
+/// How long the server gets to accept a session
+const SESSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Stops the client as soon as a session is established
+struct SessionHandler {
+    connected: AtomicBool,
+}
+
+#[async_trait]
+impl cometd::client::Handler for SessionHandler {
+    async fn on_reconnect(&self, ctx: cometd::client::Context, _resumed: bool) {
+        self.connected.store(true, Ordering::SeqCst);
+        ctx.cancel_handle().cancel();
+    }
+}
+
+/// Whether the live server accepts a cometd session with the decoded token
+async fn server_accepts(endpoints: &Endpoints, code: &str, token: &str) -> bool {
+    let url = endpoints.cometd_session_url(code, token);
+    let handler = SessionHandler {
+        connected: AtomicBool::new(false),
+    };
+
+    let mut client = match cometd::Client::connect_with_handler(&url, handler).await {
+        Ok(client) => client,
+        Err(_) => return false,
+    };
+
+    let _ = tokio::time::timeout(SESSION_TIMEOUT, client.run()).await;
+    client.handler.connected.load(Ordering::SeqCst)
+}
+
+#[tokio::main(threaded_scheduler)]
+async fn main() {
+    let mut args = std::env::args().skip(1);
+    let code = match args.next() {
+        Some(code) => code,
+        None => {
+            eprintln!("Usage: record_challenge <code> [sample name]");
+            std::process::exit(1);
+        }
+    };
+
+    let client = kahoot::challenge::Client::new();
+    let probe = match client.probe_code(&code).await {
+        Ok(probe) => probe,
+        Err(e) => {
+            eprintln!("Failed to probe code '{}': {}", code, e);
+            std::process::exit(1);
+        }
+    };
+
+    // Every backend's answer is a candidate. Only the server decides which one is right.
+    let solver = default_solver();
+    let mut candidates = Vec::new();
+    for backend in solver.solvers() {
+        let token = backend
+            .solve(probe.challenge())
+            .map_err(|e| e.to_string())
+            .and_then(|solution| decode_token(probe.token(), &solution).map_err(|e| e.to_string()));
+
+        match token {
+            Ok(token) => {
+                println!("{}: {}", backend.name(), token);
+                if !candidates.contains(&token) {
+                    candidates.push(token);
+                }
+            }
+            Err(e) => println!("{}: failed: {}", backend.name(), e),
+        }
+    }
+
+    let mut expected = None;
+    for token in candidates.iter() {
+        if server_accepts(client.get_endpoints(), &code, token).await {
+            expected = Some(token.clone());
+            break;
+        }
+    }
+
+    let expected = match expected {
+        Some(expected) => expected,
+        None => {
+            eprintln!(
+                "The server accepted none of the {} candidate tokens; not recording. Challenge:\n{}",
+                candidates.len(),
+                probe.challenge()
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let name = args.next().unwrap_or_else(|| {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("Valid SystemTime");
+        format!("sample_{}", now.as_secs())
+    });
+
+    let sample = serde_json::json!({
+        "token": probe.token(),
+        "challenge": probe.challenge(),
+        "expected": expected,
+    });
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/challenges")
+        .join(format!("{}.json", name));
+
+    if path.exists() {
+        eprintln!("'{}' already exists", path.display());
+        std::process::exit(1);
+    }
+
+    let mut data = serde_json::to_string_pretty(&sample).expect("Valid Json");
+    data.push('\n');
+
+    if let Err(e) = std::fs::write(&path, data) {
+        eprintln!("Failed to write '{}': {}", path.display(), e);
+        std::process::exit(1);
+    }
+
+    println!("Recorded '{}'", path.display());
+}
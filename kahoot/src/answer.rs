@@ -0,0 +1,196 @@
+use crate::{
+    message::GameBlockType,
+    KahootError,
+    KahootResult,
+};
+use cometd::json;
+
+/// An answer to a question, shaped by the kind of question it answers
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer {
+    /// The index of the chosen answer to a `quiz`
+    Quiz(usize),
+
+    /// The answer to a true/false question, which kahoot sends as a `quiz` with the choices true, then false
+    TrueFalse(bool),
+
+    /// The indexes of the chosen answers to a `multiple_select_quiz`
+    MultipleSelect(Vec<usize>),
+
+    /// The index of the chosen answer to a `survey`
+    Poll(usize),
+
+    /// The indexes of the chosen answers to a `multiple_select_poll`
+    MultipleSelectPoll(Vec<usize>),
+
+    /// The text typed into an `open_ended` question
+    TypeAnswer(String),
+
+    /// The order of the choices of a `jumble`, as indexes
+    Puzzle(Vec<usize>),
+
+    /// The value picked on a `slider`
+    Slider(f64),
+
+    /// The text submitted to a `word_cloud`
+    WordCloud(String),
+
+    /// The text submitted to an `open_question`
+    OpenEnded(String),
+
+    /// The idea submitted to a `brainstorming` block
+    Brainstorm(String),
+}
+
+impl Answer {
+    /// Whether this answers a question of the given block type
+    pub fn matches(&self, block_type: &GameBlockType) -> bool {
+        matches!(
+            (self, block_type),
+            (Self::Quiz(_), GameBlockType::Quiz)
+                | (Self::TrueFalse(_), GameBlockType::Quiz)
+                | (Self::MultipleSelect(_), GameBlockType::MultipleSelectQuiz)
+                | (Self::Poll(_), GameBlockType::Survey)
+                | (
+                    Self::MultipleSelectPoll(_),
+                    GameBlockType::MultipleSelectPoll
+                )
+                | (Self::TypeAnswer(_), GameBlockType::OpenEnded)
+                | (Self::Puzzle(_), GameBlockType::Jumble)
+                | (Self::Slider(_), GameBlockType::Slider)
+                | (Self::WordCloud(_), GameBlockType::WordCloud)
+                | (Self::OpenEnded(_), GameBlockType::OpenQuestion)
+                | (Self::Brainstorm(_), GameBlockType::Brainstorming)
+        )
+    }
+
+    /// Check this answers a question of the given block type with the given number of choices, if known
+    pub fn validate(&self, block_type: &GameBlockType, choices: Option<usize>) -> KahootResult<()> {
+        if !self.matches(block_type) {
+            return Err(KahootError::WrongAnswerType {
+                expected: block_type.clone(),
+                answer: self.clone(),
+            });
+        }
+
+        let choices = match choices {
+            Some(choices) => choices,
+            None => return Ok(()),
+        };
+
+        let check = |choice: usize| {
+            if choice < choices {
+                Ok(())
+            } else {
+                Err(KahootError::InvalidChoice { choice, choices })
+            }
+        };
+
+        match self {
+            Self::Quiz(choice) | Self::Poll(choice) => check(*choice),
+            Self::MultipleSelect(selected) | Self::MultipleSelectPoll(selected) => {
+                selected.iter().try_for_each(|choice| check(*choice))
+            }
+            Self::Puzzle(order) => {
+                order.iter().try_for_each(|choice| check(*choice))?;
+
+                let mut sorted = order.clone();
+                sorted.sort_unstable();
+                sorted.dedup();
+                if order.len() != choices || sorted.len() != choices {
+                    return Err(KahootError::InvalidPuzzleOrder(order.clone()));
+                }
+
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The answer fields of the `content` payload
+    pub(crate) fn content(
+        &self,
+        block_type: &GameBlockType,
+        question_index: usize,
+    ) -> serde_json::Value {
+        let mut content = json!({
+            "type": block_type.as_str(),
+            "questionIndex": question_index,
+        });
+
+        let (key, value) = match self {
+            Self::Quiz(choice) | Self::Poll(choice) => ("choice", json!(choice)),
+            Self::TrueFalse(true) => ("choice", json!(0)),
+            Self::TrueFalse(false) => ("choice", json!(1)),
+            Self::MultipleSelect(choices)
+            | Self::MultipleSelectPoll(choices)
+            | Self::Puzzle(choices) => ("choice", json!(choices)),
+            Self::Slider(value) => ("choice", json!(value)),
+            Self::TypeAnswer(text)
+            | Self::WordCloud(text)
+            | Self::OpenEnded(text)
+            | Self::Brainstorm(text) => ("text", json!(text)),
+        };
+        content[key] = value;
+
+        content
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn answer_content() {
+        assert_eq!(
+            Answer::MultipleSelect(vec![0, 2]).content(&GameBlockType::MultipleSelectQuiz, 3),
+            json!({
+                "type": "multiple_select_quiz",
+                "questionIndex": 3,
+                "choice": [0, 2],
+            })
+        );
+        assert_eq!(
+            Answer::TrueFalse(false).content(&GameBlockType::Quiz, 0),
+            json!({ "type": "quiz", "questionIndex": 0, "choice": 1 })
+        );
+        assert_eq!(
+            Answer::TypeAnswer("paris".to_string()).content(&GameBlockType::OpenEnded, 1),
+            json!({ "type": "open_ended", "questionIndex": 1, "text": "paris" })
+        );
+    }
+
+    #[test]
+    fn validate_answer() {
+        assert!(Answer::Quiz(3)
+            .validate(&GameBlockType::Quiz, Some(4))
+            .is_ok());
+        assert!(Answer::Slider(2.5)
+            .validate(&GameBlockType::Slider, None)
+            .is_ok());
+
+        match Answer::Quiz(1).validate(&GameBlockType::Jumble, Some(4)) {
+            Err(KahootError::WrongAnswerType { expected, .. }) => {
+                assert_eq!(expected, GameBlockType::Jumble)
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        match Answer::Quiz(4).validate(&GameBlockType::Quiz, Some(4)) {
+            Err(KahootError::InvalidChoice {
+                choice: 4,
+                choices: 4,
+            }) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        assert!(Answer::Puzzle(vec![2, 0, 1, 3])
+            .validate(&GameBlockType::Jumble, Some(4))
+            .is_ok());
+        match Answer::Puzzle(vec![0, 0, 1, 2]).validate(&GameBlockType::Jumble, Some(4)) {
+            Err(KahootError::InvalidPuzzleOrder(_)) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
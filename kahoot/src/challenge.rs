@@ -1,22 +1,47 @@
+mod native;
+#[cfg(feature = "ducc")]
+mod sandbox;
+mod solver;
+
+#[cfg(feature = "boa")]
+pub use self::solver::BoaSolver;
+pub use self::{
+    native::NativeDecodeError,
+    solver::{
+        default_solver,
+        ChallengeSolver,
+        FallbackSolver,
+        NativeSolver,
+    },
+};
+#[cfg(feature = "ducc")]
+pub use self::{
+    sandbox::SandboxLimits,
+    solver::DuccSolver,
+};
 use crate::{
+    Endpoints,
     KahootError,
     KahootResult,
 };
 use bytes::Buf;
-use ducc::Ducc;
 use http::{
     header::HeaderName,
     StatusCode,
 };
+#[cfg(not(feature = "tracing"))]
 use log::trace;
 use serde::Deserialize;
 #[cfg(debug_assertions)]
 use std::time::Instant;
 use std::{
-    collections::HashMap,
     string::FromUtf8Error,
+    sync::Arc,
 };
+#[cfg(feature = "tracing")]
+use tracing::trace;
 
+#[cfg(any(feature = "ducc", feature = "boa"))]
 const JS_ENV_PATCHES: &str = include_str!("js_env_patches.js");
 
 fn epoch_time_millis() -> u128 {
@@ -31,27 +56,55 @@ fn epoch_time_millis() -> u128 {
         .as_millis()
 }
 
-/// Challenge Client
+/// Challenge Client.
+///
+/// Clones share the same connection pool and solver.
+#[derive(Clone)]
 pub struct Client {
     client: hyper::Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>,
+    solver: Arc<dyn ChallengeSolver>,
+    endpoints: Endpoints,
 }
 
 impl Client {
-    /// Make a new challenge client
+    /// Make a new challenge client with the default solver
     pub fn new() -> Self {
+        Self::with_solver(default_solver())
+    }
+
+    /// Make a new challenge client that solves challenges with the given solver
+    pub fn with_solver<S: ChallengeSolver + 'static>(solver: S) -> Self {
         let https = hyper_tls::HttpsConnector::new();
         let client = hyper::Client::builder().build::<_, hyper::Body>(https);
 
-        Self { client }
+        Self {
+            client,
+            solver: Arc::new(solver),
+            endpoints: Endpoints::default(),
+        }
+    }
+
+    /// Set the endpoints to reserve sessions at.
+    ///
+    /// A `kahoot::Client` connected through this client uses them too.
+    pub fn endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Get the endpoints
+    pub fn get_endpoints(&self) -> &Endpoints {
+        &self.endpoints
     }
 
     /// Probe a code
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
     pub async fn probe_code(&self, code: &str) -> KahootResult<ProbeResult> {
         trace!("probing code '{}'", code);
 
         let url = format!(
-            "https://kahoot.it/reserve/session/{}/?{}",
-            code,
+            "{}?{}",
+            self.endpoints.reserve_session_url(code),
             epoch_time_millis()
         );
 
@@ -79,20 +132,33 @@ impl Client {
             .to_string();
 
         let body = hyper::body::aggregate(res.into_body()).await?;
-        let response: GetChallengeJsonResponse = serde_json::from_slice(body.bytes())?;
-
-        Ok(ProbeResult { token, response })
+        let raw: serde_json::Value = serde_json::from_slice(body.bytes())?;
+        let ChallengeJsonResponse { challenge } = serde_json::from_value(raw.clone())?;
+        let mut session_info: SessionInfo = serde_json::from_value(raw.clone())?;
+        session_info.raw = raw;
+
+        Ok(ProbeResult {
+            token,
+            challenge,
+            session_info,
+        })
     }
 
     /// Get the token for a code
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
     pub async fn get_token(&self, code: &str) -> KahootResult<String> {
         let res = self.probe_code(code).await?;
+        self.solve(res).await
+    }
 
+    /// Solve the challenge of a probe, getting its session token
+    pub async fn solve(&self, res: ProbeResult) -> KahootResult<String> {
         #[cfg(debug_assertions)]
         let start = Instant::now();
 
+        let solver = self.solver.clone();
         let token = tokio::task::spawn_blocking(move || {
-            crate::challenge::decode(&res.token, &res.response.challenge)
+            crate::challenge::decode_with(&*solver, &res.token, &res.challenge)
         })
         .await??;
 
@@ -113,31 +179,105 @@ impl Default for Client {
 #[derive(Debug)]
 pub struct ProbeResult {
     token: String,
-    response: GetChallengeJsonResponse,
+    challenge: String,
+    session_info: SessionInfo,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct GetChallengeJsonResponse {
-    #[serde(rename = "twoFactorAuth")]
-    two_factor_auth: bool,
+impl ProbeResult {
+    /// The encoded session token
+    pub fn token(&self) -> &str {
+        &self.token
+    }
 
-    namerator: bool,
+    /// The challenge js that decodes the session token
+    pub fn challenge(&self) -> &str {
+        &self.challenge
+    }
 
-    #[serde(rename = "participantId")]
-    participant_id: Option<serde_json::Value>,
+    /// Whether players must log in with generated names
+    pub fn namerator(&self) -> bool {
+        self.session_info.namerator
+    }
 
-    #[serde(rename = "smartPractice")]
-    smart_practice: bool,
+    /// What kind of game the session is
+    pub fn session_info(&self) -> &SessionInfo {
+        &self.session_info
+    }
+}
 
+#[derive(Deserialize)]
+struct ChallengeJsonResponse {
     challenge: String,
+}
+
+/// What kind of game a session is, as reported when probing its code
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionInfo {
+    /// Whether players must enter a two-factor pattern shown on the host's screen
+    #[serde(rename = "twoFactorAuth", default)]
+    pub two_factor_auth: bool,
+
+    /// Whether players must log in with generated names
+    #[serde(default)]
+    pub namerator: bool,
+
+    #[serde(rename = "participantId", default)]
+    pub participant_id: Option<serde_json::Value>,
+
+    /// Whether the game is a smart practice game
+    #[serde(rename = "smartPractice", default)]
+    pub smart_practice: bool,
+
+    /// Whether the game has collaborations turned on
+    #[serde(default)]
+    pub collaborations: bool,
+
+    /// The game mode, if reported
+    #[serde(rename = "gameMode", default)]
+    pub game_mode: Option<GameMode>,
+
+    #[serde(rename = "liveGameId", default)]
+    pub live_game_id: Option<String>,
+
+    /// The whole response, including fields not covered above
+    #[serde(skip)]
+    pub raw: serde_json::Value,
+}
+
+impl SessionInfo {
+    /// Whether players join in teams
+    pub fn team_mode(&self) -> bool {
+        self.game_mode == Some(GameMode::Team)
+    }
+}
 
-    #[serde(flatten)]
-    extra: HashMap<String, serde_json::Value>,
+/// How players take part in a game
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub enum GameMode {
+    /// Every player plays alone
+    Classic,
+
+    /// Players play in teams
+    Team,
+
+    Other(String),
+}
+
+impl From<String> for GameMode {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "classic" | "normal" => Self::Classic,
+            "team" => Self::Team,
+            _ => Self::Other(s),
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum DecodeError {
     /// Challenge decode error
+    #[cfg(feature = "ducc")]
     #[error("{0}")]
     ChallegeDecode(#[from] SendDuccError),
 
@@ -145,11 +285,40 @@ pub enum DecodeError {
     #[error("boa challenge decode error")]
     ChallegeDecodeBoa(String),
 
+    /// Native Challenge decode error
+    #[error("{0}")]
+    ChallengeDecodeNative(#[from] NativeDecodeError),
+
+    /// A challenge ran past its deadline
+    #[error("challenge did not finish in {0:?}")]
+    ChallengeTimeout(std::time::Duration),
+
+    /// A challenge is longer than the sandbox allows
+    #[error("challenge is {len} bytes, the limit is {max}")]
+    ChallengeTooLarge { len: usize, max: usize },
+
+    /// A challenge produced a solution longer than the sandbox allows
+    #[error("challenge solution is {len} bytes, the limit is {max}")]
+    SolutionTooLarge { len: usize, max: usize },
+
+    /// A challenge tried to use more memory than the sandbox allows
+    #[error("challenge used more than {0} bytes of memory")]
+    ChallengeMemoryExceeded(usize),
+
+    /// The process a challenge ran in could not be started, or died without a result
+    #[error("challenge sandbox failed: {0}")]
+    SandboxFailed(String),
+
+    /// Every solver in a fallback chain failed. Holds each solver's error, in order.
+    #[error("all challenge solvers failed")]
+    AllSolversFailed(Vec<DecodeError>),
+
     /// Token deocode error
     #[error("{0}")]
     TokenDecode(#[from] DecodeTokenError),
 }
 
+#[cfg(feature = "ducc")]
 impl From<ducc::Error> for DecodeError {
     fn from(e: ducc::Error) -> Self {
         DecodeError::ChallegeDecode(e.into())
@@ -157,6 +326,7 @@ impl From<ducc::Error> for DecodeError {
 }
 
 /// Sendable DuccError
+#[cfg(feature = "ducc")]
 #[derive(Debug, thiserror::Error)]
 pub struct SendDuccError {
     /// Error Kind
@@ -167,12 +337,14 @@ pub struct SendDuccError {
     pub context: Vec<String>,
 }
 
+#[cfg(feature = "ducc")]
 impl std::fmt::Display for SendDuccError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.kind.fmt(f)
     }
 }
 
+#[cfg(feature = "ducc")]
 impl From<ducc::Error> for SendDuccError {
     fn from(e: ducc::Error) -> Self {
         Self {
@@ -182,18 +354,13 @@ impl From<ducc::Error> for SendDuccError {
     }
 }
 
+#[cfg(feature = "ducc")]
 #[derive(Debug, thiserror::Error)]
 pub enum SendDuccErrorKind {
     #[error("to js conversion error")]
-    ToJsConversionError {
-        from: &'static str,
-        to: &'static str,
-    },
+    ToJsConversionError { from: String, to: String },
     #[error("from js conversion error")]
-    FromJsConversionError {
-        from: &'static str,
-        to: &'static str,
-    },
+    FromJsConversionError { from: String, to: String },
     #[error("runtime error")]
     RuntimeError {
         code: ducc::RuntimeErrorCode,
@@ -209,14 +376,21 @@ pub enum SendDuccErrorKind {
     NotAFunction,
 }
 
+#[cfg(feature = "ducc")]
 impl From<ducc::ErrorKind> for SendDuccErrorKind {
     fn from(e: ducc::ErrorKind) -> Self {
         match e {
             ducc::ErrorKind::ToJsConversionError { from, to } => {
-                SendDuccErrorKind::ToJsConversionError { from, to }
+                SendDuccErrorKind::ToJsConversionError {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                }
             }
             ducc::ErrorKind::FromJsConversionError { from, to } => {
-                SendDuccErrorKind::FromJsConversionError { from, to }
+                SendDuccErrorKind::FromJsConversionError {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                }
             }
             ducc::ErrorKind::RuntimeError { code, name } => {
                 SendDuccErrorKind::RuntimeError { code, name }
@@ -229,23 +403,32 @@ impl From<ducc::ErrorKind> for SendDuccErrorKind {
 }
 
 /// Decode a token
+#[cfg(feature = "ducc")]
 pub fn decode(encoded_token: &str, encoded_challenge: &str) -> Result<String, DecodeError> {
     let decoded_challenge = decode_challenge(encoded_challenge)?;
     let decoded_token = decode_token(encoded_token, &decoded_challenge)?;
     Ok(decoded_token)
 }
 
-/// Decode a challenge
-pub fn decode_challenge(challenge_str: &str) -> ducc::Result<String> {
-    thread_local!(static DUCC: Ducc = Ducc::new());
+/// Decode a token, solving the challenge with the given solver
+pub fn decode_with(
+    solver: &dyn ChallengeSolver,
+    encoded_token: &str,
+    encoded_challenge: &str,
+) -> Result<String, DecodeError> {
+    let decoded_challenge = solver.solve(encoded_challenge)?;
+    let decoded_token = decode_token(encoded_token, &decoded_challenge)?;
+    Ok(decoded_token)
+}
 
-    DUCC.with(|ducc| {
-        ducc.exec(JS_ENV_PATCHES, Some("env_patches.js"), Default::default())?;
-        ducc.exec(challenge_str, Some("challenge.js"), Default::default())
-    })
+/// Decode a challenge in a fresh ducc context with the default `SandboxLimits`
+#[cfg(feature = "ducc")]
+pub fn decode_challenge(challenge_str: &str) -> Result<String, DecodeError> {
+    sandbox::exec(challenge_str, &SandboxLimits::default())
 }
 
 /// Decode a token with boa
+#[cfg(feature = "boa")]
 pub fn decode_boa(encoded_token: &str, encoded_challenge: &str) -> Result<String, DecodeError> {
     let decoded_challenge =
         decode_challenge_boa(encoded_challenge).map_err(DecodeError::ChallegeDecodeBoa)?;
@@ -254,6 +437,7 @@ pub fn decode_boa(encoded_token: &str, encoded_challenge: &str) -> Result<String
 }
 
 /// Decode a challenge with boa. Boa currently cannot do this so this will always fail
+#[cfg(feature = "boa")]
 pub fn decode_challenge_boa(challenge_str: &str) -> Result<String, String> {
     let mut ctx = boa::Context::new();
     let _patches = ctx.eval(JS_ENV_PATCHES).map_err(|e| {
@@ -276,6 +460,20 @@ pub fn decode_challenge_boa(challenge_str: &str) -> Result<String, String> {
     Ok(challenge)
 }
 
+/// Decode a token without a js engine
+pub fn decode_native(encoded_token: &str, encoded_challenge: &str) -> Result<String, DecodeError> {
+    let decoded_challenge = decode_challenge_native(encoded_challenge)?;
+    let decoded_token = decode_token(encoded_token, &decoded_challenge)?;
+    Ok(decoded_token)
+}
+
+/// Decode a challenge by parsing its expected shape instead of running it.
+///
+/// Fails with a `NativeDecodeError` if the challenge does not have the shape kahoot currently uses.
+pub fn decode_challenge_native(challenge_str: &str) -> Result<String, NativeDecodeError> {
+    native::decode_challenge(challenge_str)
+}
+
 /// Decode Token Error
 #[derive(Debug, thiserror::Error)]
 pub enum DecodeTokenError {
@@ -286,12 +484,19 @@ pub enum DecodeTokenError {
     /// Invalid string error
     #[error("{0}")]
     InvalidString(#[from] FromUtf8Error),
+
+    /// The challenge solution is empty, so there is nothing to unmask the token with
+    #[error("empty challenge solution")]
+    EmptyChallenge,
 }
 
 pub fn decode_token(token: &str, challenge: &str) -> Result<String, DecodeTokenError> {
     let mut raw_token = base64::decode(token)?;
     let challenge_bytes = challenge.as_bytes();
     let challenge_len = challenge_bytes.len();
+    if challenge_len == 0 {
+        return Err(DecodeTokenError::EmptyChallenge);
+    }
 
     for (i, byte) in raw_token.iter_mut().enumerate() {
         *byte ^= challenge_bytes[i % challenge_len];
@@ -303,7 +508,8 @@ pub fn decode_token(token: &str, challenge: &str) -> Result<String, DecodeTokenE
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::time::Instant;
+    #[cfg(feature = "ducc")]
+    use std::time::Duration;
 
     const SAMPLE_1: (&str, &str) = (
         "UFJ5AUhQO1J9SlcHA3BBYUQCU1xzCFFiPjYyekFDAwZIDzN+ISAIfwIgDVtfUjh2MAAJP0JpXnZjR0QicA5/BlkLQEQCGElMflFDSlkHAUpZa1MODAwHTnhHHg1XaT9+",
@@ -311,7 +517,10 @@ mod test {
     );
 
     #[test]
+    #[cfg(feature = "ducc")]
     fn decode_sample_1() {
+        use std::time::Instant;
+
         let start = Instant::now();
         assert_eq!(decode(SAMPLE_1.0, SAMPLE_1.1).unwrap(), "2f8648fc7031b16045414732dde566f309a8aa296e2720d7db9a82a7827a7f7d7f854b946e839ef3481140a994d5a8b2");
         let end = Instant::now();
@@ -323,8 +532,154 @@ mod test {
         dbg!(end - start);
     }
 
+    #[test]
+    #[cfg(feature = "ducc")]
+    fn ducc_sandbox_limits() {
+        let solver = DuccSolver::new().limits(SandboxLimits::new().max_source_len(16));
+        match solver.solve(SAMPLE_1.1) {
+            Err(DecodeError::ChallengeTooLarge { max: 16, .. }) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        let solver =
+            DuccSolver::new().limits(SandboxLimits::new().timeout(Duration::from_millis(100)));
+        match solver.solve("while (true) {}") {
+            Err(DecodeError::ChallengeTimeout(_)) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        // Globals outside the whitelist are gone, and nothing leaks between challenges
+        assert_eq!(solver.solve("typeof Duktape").unwrap(), "undefined");
+        assert_eq!(
+            solver.solve("this.leak = 'leak'; typeof leak").unwrap(),
+            "string"
+        );
+        assert_eq!(solver.solve("typeof leak").unwrap(), "undefined");
+    }
+
+    #[test]
+    #[cfg(all(feature = "ducc", target_os = "linux"))]
+    fn ducc_memory_limit() {
+        let max_memory = 16 * 1024 * 1024;
+        let solver = DuccSolver::new().limits(SandboxLimits::new().max_memory(max_memory));
+
+        // Doubling a string passes the cap in a couple dozen iterations, long before the deadline
+        match solver.solve("var s = 'x'; while (true) { s = s + s; }") {
+            Err(DecodeError::ChallengeMemoryExceeded(max)) => assert_eq!(max, max_memory),
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        // Only the challenge's process ran out
+        assert_eq!(solver.solve("'ok'").unwrap(), "ok");
+    }
+
+    #[test]
+    fn decode_sample_1_native() {
+        assert_eq!(decode_native(SAMPLE_1.0, SAMPLE_1.1).unwrap(), "2f8648fc7031b16045414732dde566f309a8aa296e2720d7db9a82a7827a7f7d7f854b946e839ef3481140a994d5a8b2");
+    }
+
+    #[test]
+    fn decode_unknown_shape_native() {
+        assert_eq!(
+            decode_challenge_native("eval(atob('Zm9v'))").unwrap_err(),
+            NativeDecodeError::MissingMessage
+        );
+
+        let bad_offset = SAMPLE_1
+            .1
+            .replace("var offset = 75", "var offset = Math.PI");
+        assert_eq!(
+            decode_challenge_native(&bad_offset).unwrap_err(),
+            NativeDecodeError::UnexpectedToken("PI".into())
+        );
+    }
+
+    #[test]
+    fn decode_nested_native() {
+        let offset = |expr: &str| {
+            SAMPLE_1
+                .1
+                .replace("var offset = 75", &format!("var offset = {} + 75", expr))
+        };
+
+        let nested = format!("{}0{}", "(".repeat(100), ")".repeat(100));
+        assert_eq!(
+            decode_challenge_native(&offset(&nested)).unwrap(),
+            decode_challenge_native(SAMPLE_1.1).unwrap()
+        );
+
+        let parens = format!("{}0{}", "(".repeat(300), ")".repeat(300));
+        assert_eq!(
+            decode_challenge_native(&offset(&parens)).unwrap_err(),
+            NativeDecodeError::TooDeep(256)
+        );
+
+        let negations = format!("{}0", "-".repeat(300));
+        assert_eq!(
+            decode_challenge_native(&offset(&negations)).unwrap_err(),
+            NativeDecodeError::TooDeep(256)
+        );
+
+        let parens = format!("{}0{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert_eq!(
+            decode_challenge_native(&offset(&parens)).unwrap_err(),
+            NativeDecodeError::TooLong(1024)
+        );
+
+        let sum = "1+".repeat(100_000);
+        assert_eq!(
+            decode_challenge_native(&offset(&sum)).unwrap_err(),
+            NativeDecodeError::TooLong(1024)
+        );
+    }
+
+    #[test]
+    fn decode_empty_solution() {
+        struct EmptySolver;
+
+        impl ChallengeSolver for EmptySolver {
+            fn name(&self) -> &str {
+                "empty"
+            }
+
+            fn solve(&self, _challenge: &str) -> Result<String, DecodeError> {
+                Ok(String::new())
+            }
+        }
+
+        match decode_with(&EmptySolver, SAMPLE_1.0, SAMPLE_1.1).unwrap_err() {
+            DecodeError::TokenDecode(DecodeTokenError::EmptyChallenge) => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn decode_sample_1_fallback() {
+        struct FailingSolver;
+
+        impl ChallengeSolver for FailingSolver {
+            fn name(&self) -> &str {
+                "failing"
+            }
+
+            fn solve(&self, _challenge: &str) -> Result<String, DecodeError> {
+                Err(DecodeError::ChallegeDecodeBoa("unsupported".into()))
+            }
+        }
+
+        let solver = FallbackSolver::new().with(FailingSolver).with(NativeSolver);
+        assert_eq!(decode_with(&solver, SAMPLE_1.0, SAMPLE_1.1).unwrap(), "2f8648fc7031b16045414732dde566f309a8aa296e2720d7db9a82a7827a7f7d7f854b946e839ef3481140a994d5a8b2");
+
+        let solver = FallbackSolver::new().with(FailingSolver);
+        match decode_with(&solver, SAMPLE_1.0, SAMPLE_1.1).unwrap_err() {
+            DecodeError::AllSolversFailed(errors) => assert_eq!(errors.len(), 1),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
     #[test]
     #[ignore]
+    #[cfg(feature = "boa")]
     fn decode_sample_1_boa() {
         assert_eq!(decode_boa(SAMPLE_1.0, SAMPLE_1.1).unwrap(), "2f8648fc7031b16045414732dde566f309a8aa296e2720d7db9a82a7827a7f7d7f854b946e839ef3481140a994d5a8b2");
     }
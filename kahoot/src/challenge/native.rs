@@ -0,0 +1,486 @@
+//! A challenge solver that understands the shape of kahoot's challenges instead of running them.
+//!
+//! Challenges look like this, padded with unicode whitespace:
+//! ```text
+//! decode.call(this, '<message>'); function decode(message) {var offset = <arithmetic>; <noise> return _.replace(message,/./g, function(char, position) {return String.fromCharCode(<arithmetic>);});}
+//! ```
+//! The offset is evaluated once, then every char of the message is mapped through the `fromCharCode` expression.
+
+use std::iter::Peekable;
+
+const CALL_PREFIX: &str = "decode.call(";
+const OFFSET_PREFIX: &str = "var offset";
+const REPLACE_CALLBACK: &str = "function(";
+const FROM_CHAR_CODE: &str = "String.fromCharCode(";
+const CHAR_CODE_AT: &str = "charCodeAt";
+const OFFSET_NAME: &str = "offset";
+
+/// How deeply parentheses, unary operators and calls may nest in an expression
+const MAX_DEPTH: usize = 256;
+
+/// The most tokens an expression may have. This also bounds how deep the parsed tree is.
+const MAX_TOKENS: usize = 1024;
+
+/// A challenge that does not have the expected shape
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum NativeDecodeError {
+    /// The `decode.call(this, '...')` message is missing
+    #[error("missing challenge message")]
+    MissingMessage,
+
+    /// The message string literal is malformed
+    #[error("invalid challenge message literal")]
+    InvalidMessage,
+
+    /// The `var offset = ...;` expression is missing
+    #[error("missing offset expression")]
+    MissingOffset,
+
+    /// The `function(char, position)` replace callback is missing
+    #[error("missing replace callback")]
+    MissingCallback,
+
+    /// The `String.fromCharCode(...)` expression is missing
+    #[error("missing fromCharCode expression")]
+    MissingTransform,
+
+    /// An expression contains a token that is not supported
+    #[error("unexpected token '{0}'")]
+    UnexpectedToken(String),
+
+    /// An expression ended early
+    #[error("unexpected end of expression")]
+    UnexpectedEnd,
+
+    /// An expression uses a name that is not bound
+    #[error("unknown identifier '{0}'")]
+    UnknownIdentifier(String),
+
+    /// An expression nests deeper than the parser allows
+    #[error("expression nested deeper than {0} levels")]
+    TooDeep(usize),
+
+    /// An expression has more tokens than the parser allows
+    #[error("expression longer than {0} tokens")]
+    TooLong(usize),
+}
+
+/// Decode a challenge without a js engine
+pub fn decode_challenge(challenge: &str) -> Result<String, NativeDecodeError> {
+    let message = parse_message(challenge)?;
+
+    let offset_expr = after(challenge, OFFSET_PREFIX).ok_or(NativeDecodeError::MissingOffset)?;
+    let offset_expr = skip_whitespace(offset_expr)
+        .strip_prefix('=')
+        .ok_or(NativeDecodeError::MissingOffset)?;
+    let offset_end = offset_expr
+        .find(';')
+        .ok_or(NativeDecodeError::MissingOffset)?;
+    let offset = Expr::parse(&offset_expr[..offset_end])?.eval(&Scope::default())?;
+
+    let callback = after(challenge, REPLACE_CALLBACK).ok_or(NativeDecodeError::MissingCallback)?;
+    let params_end = callback
+        .find(')')
+        .ok_or(NativeDecodeError::MissingCallback)?;
+    let mut params = callback[..params_end].split(',').map(|param| param.trim());
+    let (char_name, position_name) = match (params.next(), params.next(), params.next()) {
+        (Some(char_name), Some(position_name), None)
+            if is_identifier(char_name) && is_identifier(position_name) =>
+        {
+            (char_name, position_name)
+        }
+        _ => return Err(NativeDecodeError::MissingCallback),
+    };
+
+    let transform = after(callback, FROM_CHAR_CODE).ok_or(NativeDecodeError::MissingTransform)?;
+    let transform_end = matching_paren(transform).ok_or(NativeDecodeError::MissingTransform)?;
+    let transform = Expr::parse(&transform[..transform_end])?;
+
+    let mut scope = Scope {
+        offset,
+        char_name,
+        position_name,
+        char_code: 0.0,
+        position: 0.0,
+    };
+
+    // `/./g` visits each utf16 code unit that is not a line terminator
+    let mut decoded = Vec::with_capacity(message.len());
+    for (position, code_unit) in message.encode_utf16().enumerate() {
+        if is_line_terminator(code_unit) {
+            decoded.push(code_unit);
+            continue;
+        }
+
+        scope.char_code = f64::from(code_unit);
+        scope.position = position as f64;
+        decoded.push(to_uint16(transform.eval(&scope)?));
+    }
+
+    Ok(String::from_utf16_lossy(&decoded))
+}
+
+/// Extract the string passed to `decode.call`
+fn parse_message(challenge: &str) -> Result<String, NativeDecodeError> {
+    let args = after(challenge, CALL_PREFIX).ok_or(NativeDecodeError::MissingMessage)?;
+    let args = skip_whitespace(args)
+        .strip_prefix("this")
+        .ok_or(NativeDecodeError::MissingMessage)?;
+    let args = skip_whitespace(args)
+        .strip_prefix(',')
+        .ok_or(NativeDecodeError::MissingMessage)?;
+    let mut chars = skip_whitespace(args).chars();
+
+    let quote = match chars.next() {
+        Some(quote @ '\'') | Some(quote @ '"') => quote,
+        _ => return Err(NativeDecodeError::MissingMessage),
+    };
+
+    let mut message = String::new();
+    loop {
+        match chars.next().ok_or(NativeDecodeError::InvalidMessage)? {
+            c if c == quote => return Ok(message),
+            '\\' => match chars.next().ok_or(NativeDecodeError::InvalidMessage)? {
+                'n' => message.push('\n'),
+                'r' => message.push('\r'),
+                't' => message.push('\t'),
+                '0' => message.push('\0'),
+                'x' => message.push(parse_hex_escape(&mut chars, 2)?),
+                'u' => message.push(parse_hex_escape(&mut chars, 4)?),
+                c => message.push(c),
+            },
+            '\n' | '\r' => return Err(NativeDecodeError::InvalidMessage),
+            c => message.push(c),
+        }
+    }
+}
+
+fn parse_hex_escape(
+    chars: &mut std::str::Chars<'_>,
+    len: usize,
+) -> Result<char, NativeDecodeError> {
+    let hex: String = chars.take(len).collect();
+    if hex.len() != len {
+        return Err(NativeDecodeError::InvalidMessage);
+    }
+
+    u32::from_str_radix(&hex, 16)
+        .ok()
+        .and_then(std::char::from_u32)
+        .ok_or(NativeDecodeError::InvalidMessage)
+}
+
+/// Get the rest of the string after the first occurence of a pattern.
+///
+/// Challenges are padded with whitespace between tokens, so any amount of whitespace may appear between
+/// the chars of the pattern, and whitespace in the pattern matches one or more whitespace chars.
+fn after<'a>(haystack: &'a str, pattern: &str) -> Option<&'a str> {
+    'outer: for (start, _) in haystack.char_indices() {
+        let mut rest = &haystack[start..];
+        for (i, expected) in pattern.chars().enumerate() {
+            if expected.is_whitespace() {
+                let trimmed = skip_whitespace(rest);
+                if trimmed.len() == rest.len() {
+                    continue 'outer;
+                }
+
+                rest = trimmed;
+                continue;
+            }
+
+            if i != 0 {
+                rest = skip_whitespace(rest);
+            }
+
+            let mut chars = rest.chars();
+            if chars.next() != Some(expected) {
+                continue 'outer;
+            }
+            rest = chars.as_str();
+        }
+
+        return Some(rest);
+    }
+
+    None
+}
+
+/// Find the index of the paren that closes an already opened paren
+fn matching_paren(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn skip_whitespace(s: &str) -> &str {
+    s.trim_start_matches(is_whitespace)
+}
+
+/// Whether a char is js whitespace or a line terminator
+fn is_whitespace(c: char) -> bool {
+    c.is_whitespace() || c == '\u{FEFF}'
+}
+
+fn is_line_terminator(code_unit: u16) -> bool {
+    matches!(code_unit, 0x000A | 0x000D | 0x2028 | 0x2029)
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => {}
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// The js `ToUint16` conversion used by `String.fromCharCode`
+fn to_uint16(n: f64) -> u16 {
+    if !n.is_finite() {
+        return 0;
+    }
+
+    n.trunc().rem_euclid(65536.0) as u16
+}
+
+/// The names an expression can refer to
+#[derive(Default)]
+struct Scope<'a> {
+    offset: f64,
+    char_name: &'a str,
+    position_name: &'a str,
+    char_code: f64,
+    position: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Punct(char),
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, NativeDecodeError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if is_whitespace(c) {
+            chars.next();
+        } else if c.is_ascii_digit()
+            || (c == '.' && s[start + 1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let end = take_while(&mut chars, |c| c.is_ascii_digit() || c == '.');
+            let number = s[start..end]
+                .parse()
+                .map_err(|_| NativeDecodeError::UnexpectedToken(s[start..end].to_string()))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '$' {
+            let end = take_while(&mut chars, |c| {
+                c.is_ascii_alphanumeric() || c == '_' || c == '$'
+            });
+            tokens.push(Token::Identifier(s[start..end].to_string()));
+        } else if "+-*/%().".contains(c) {
+            chars.next();
+            tokens.push(Token::Punct(c));
+        } else {
+            return Err(NativeDecodeError::UnexpectedToken(c.to_string()));
+        }
+
+        if tokens.len() > MAX_TOKENS {
+            return Err(NativeDecodeError::TooLong(MAX_TOKENS));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Consume chars while the predicate holds, returning the end index
+fn take_while<I, F>(chars: &mut Peekable<I>, predicate: F) -> usize
+where
+    I: Iterator<Item = (usize, char)>,
+    F: Fn(char) -> bool,
+{
+    let mut end = 0;
+    while let Some(&(i, c)) = chars.peek() {
+        if !predicate(c) {
+            return i;
+        }
+
+        end = i + c.len_utf8();
+        chars.next();
+    }
+
+    end
+}
+
+/// An arithmetic expression
+#[derive(Debug)]
+enum Expr {
+    Number(f64),
+    Variable(String),
+    /// `<name>.charCodeAt(<index>)`
+    CharCodeAt(String, Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn parse(s: &str) -> Result<Self, NativeDecodeError> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser {
+            tokens: tokens.into_iter().peekable(),
+            depth: 0,
+        };
+
+        let expr = parser.parse_sum()?;
+        match parser.tokens.next() {
+            Some(token) => Err(unexpected(token)),
+            None => Ok(expr),
+        }
+    }
+
+    fn eval(&self, scope: &Scope) -> Result<f64, NativeDecodeError> {
+        Ok(match self {
+            Expr::Number(n) => *n,
+            Expr::Variable(name) if name == OFFSET_NAME => scope.offset,
+            Expr::Variable(name) if name == scope.position_name => scope.position,
+            Expr::Variable(name) => return Err(NativeDecodeError::UnknownIdentifier(name.clone())),
+            Expr::CharCodeAt(name, index) if name == scope.char_name => {
+                // The char is a single code unit, so any other index is out of range
+                if index.eval(scope)? == 0.0 {
+                    scope.char_code
+                } else {
+                    f64::NAN
+                }
+            }
+            Expr::CharCodeAt(name, _) => {
+                return Err(NativeDecodeError::UnknownIdentifier(name.clone()))
+            }
+            Expr::Neg(expr) => -expr.eval(scope)?,
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = lhs.eval(scope)?;
+                let rhs = rhs.eval(scope)?;
+                match op {
+                    '+' => lhs + rhs,
+                    '-' => lhs - rhs,
+                    '*' => lhs * rhs,
+                    '/' => lhs / rhs,
+                    // Rust's float remainder has the same semantics as js
+                    '%' => lhs % rhs,
+                    _ => unreachable!("unknown operator '{}'", op),
+                }
+            }
+        })
+    }
+}
+
+struct Parser {
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+
+    /// How many `parse_unary` calls are on the stack
+    depth: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Result<Token, NativeDecodeError> {
+        self.tokens.next().ok_or(NativeDecodeError::UnexpectedEnd)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), NativeDecodeError> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(unexpected(token)),
+        }
+    }
+
+    fn next_op(&mut self, ops: &str) -> Option<char> {
+        match self.tokens.peek() {
+            Some(&Token::Punct(op)) if ops.contains(op) => {
+                self.tokens.next();
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, NativeDecodeError> {
+        let mut expr = self.parse_product()?;
+        while let Some(op) = self.next_op("+-") {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.parse_product()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_product(&mut self) -> Result<Expr, NativeDecodeError> {
+        let mut expr = self.parse_unary()?;
+        while let Some(op) = self.next_op("*/%") {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.parse_unary()?));
+        }
+
+        Ok(expr)
+    }
+
+    /// Every recursive path goes through here, so this is where nesting is limited
+    fn parse_unary(&mut self) -> Result<Expr, NativeDecodeError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(NativeDecodeError::TooDeep(MAX_DEPTH));
+        }
+
+        self.depth += 1;
+        let result = match self.next_op("+-") {
+            Some('-') => self.parse_unary().map(|expr| Expr::Neg(Box::new(expr))),
+            Some(_) => self.parse_unary(),
+            None => self.parse_primary(),
+        };
+        self.depth -= 1;
+
+        result
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, NativeDecodeError> {
+        match self.next()? {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Punct('(') => {
+                let expr = self.parse_sum()?;
+                self.expect(Token::Punct(')'))?;
+                Ok(expr)
+            }
+            Token::Identifier(name) => {
+                if self.next_op(".").is_none() {
+                    return Ok(Expr::Variable(name));
+                }
+
+                match self.next()? {
+                    Token::Identifier(method) if method == CHAR_CODE_AT => {}
+                    token => return Err(unexpected(token)),
+                }
+
+                self.expect(Token::Punct('('))?;
+                let index = self.parse_sum()?;
+                self.expect(Token::Punct(')'))?;
+
+                Ok(Expr::CharCodeAt(name, Box::new(index)))
+            }
+            token => Err(unexpected(token)),
+        }
+    }
+}
+
+fn unexpected(token: Token) -> NativeDecodeError {
+    NativeDecodeError::UnexpectedToken(match token {
+        Token::Number(n) => n.to_string(),
+        Token::Identifier(name) => name,
+        Token::Punct(c) => c.to_string(),
+    })
+}
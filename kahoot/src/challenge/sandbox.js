@@ -0,0 +1,36 @@
+// Runs before anything else in a fresh context, deleting every global that is not needed to solve a challenge.
+(function (global) {
+    var allowed = {
+        "Object": true,
+        "Function": true,
+        "Array": true,
+        "String": true,
+        "Number": true,
+        "Boolean": true,
+        "Math": true,
+        "RegExp": true,
+        "Error": true,
+        "TypeError": true,
+        "RangeError": true,
+        "SyntaxError": true,
+        "ReferenceError": true,
+        "parseInt": true,
+        "parseFloat": true,
+        "isNaN": true,
+        "isFinite": true,
+        "NaN": true,
+        "Infinity": true,
+        "undefined": true
+    };
+
+    var names = Object.getOwnPropertyNames(global);
+    for (var i = 0; i < names.length; i++) {
+        if (!allowed.hasOwnProperty(names[i])) {
+            try {
+                delete global[names[i]];
+            } catch (e) {
+                // Non-configurable globals stay, but none of them are capabilities.
+            }
+        }
+    }
+})(this);
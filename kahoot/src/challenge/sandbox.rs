@@ -0,0 +1,152 @@
+//! Running challenges in Duktape with limits.
+//!
+//! Every challenge gets a fresh `Ducc`, so nothing a challenge does can leak into the next one.
+//! Globals that are not needed to solve a challenge are deleted before the challenge runs.
+//!
+//! On Linux, each challenge runs in a forked child process with its data segment capped by `RLIMIT_DATA`,
+//! so a challenge that allocates too much fails instead of taking memory from the caller.
+//! ducc does not expose Duktape's allocator, so there is no way to cap memory in-process,
+//! and other platforms run challenges on the calling thread without a memory cap.
+
+#[cfg(target_os = "linux")]
+mod process;
+
+use super::{
+    DecodeError,
+    JS_ENV_PATCHES,
+};
+use ducc::{
+    Ducc,
+    ExecSettings,
+};
+use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+const SANDBOX_PRELUDE: &str = include_str!("sandbox.js");
+
+/// Limits on running a challenge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandboxLimits {
+    /// How long a challenge may run before it is interrupted
+    pub timeout: Duration,
+
+    /// The longest challenge source that will be run, in bytes
+    pub max_source_len: usize,
+
+    /// The longest solution that will be accepted, in bytes
+    pub max_solution_len: usize,
+
+    /// The most memory a challenge may allocate, in bytes. Only enforced on Linux; see the module docs.
+    pub max_memory: usize,
+}
+
+impl SandboxLimits {
+    /// Make the default limits
+    pub fn new() -> Self {
+        Self {
+            timeout: Duration::from_secs(2),
+            max_source_len: 64 * 1024,
+            max_solution_len: 16 * 1024,
+            max_memory: 32 * 1024 * 1024,
+        }
+    }
+
+    /// Set the execution deadline
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the longest challenge source that will be run
+    pub fn max_source_len(mut self, max_source_len: usize) -> Self {
+        self.max_source_len = max_source_len;
+        self
+    }
+
+    /// Set the longest solution that will be accepted
+    pub fn max_solution_len(mut self, max_solution_len: usize) -> Self {
+        self.max_solution_len = max_solution_len;
+        self
+    }
+
+    /// Set the most memory a challenge may allocate
+    pub fn max_memory(mut self, max_memory: usize) -> Self {
+        self.max_memory = max_memory;
+        self
+    }
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Run a challenge in a fresh, locked down context
+pub(super) fn exec(challenge: &str, limits: &SandboxLimits) -> Result<String, DecodeError> {
+    if challenge.len() > limits.max_source_len {
+        return Err(DecodeError::ChallengeTooLarge {
+            len: challenge.len(),
+            max: limits.max_source_len,
+        });
+    }
+
+    run_isolated(challenge, limits)
+}
+
+/// Run a challenge in a child process, so its memory can be capped
+#[cfg(target_os = "linux")]
+fn run_isolated(challenge: &str, limits: &SandboxLimits) -> Result<String, DecodeError> {
+    process::exec(challenge, limits)
+}
+
+/// Run a challenge on this thread, as its memory cannot be capped here
+#[cfg(not(target_os = "linux"))]
+fn run_isolated(challenge: &str, limits: &SandboxLimits) -> Result<String, DecodeError> {
+    run(challenge, limits)
+}
+
+/// Run a challenge on this thread, enforcing every limit but the memory cap
+fn run(challenge: &str, limits: &SandboxLimits) -> Result<String, DecodeError> {
+    let ducc = Ducc::new();
+    let deadline = Instant::now() + limits.timeout;
+    let interrupted = Rc::new(Cell::new(false));
+    let settings = || {
+        let interrupted = interrupted.clone();
+        ExecSettings {
+            cancel_fn: Some(Box::new(move || {
+                let expired = Instant::now() > deadline;
+                if expired {
+                    interrupted.set(true);
+                }
+                expired
+            })),
+        }
+    };
+
+    let result = ducc
+        .exec::<()>(SANDBOX_PRELUDE, Some("sandbox.js"), settings())
+        .and_then(|_| ducc.exec::<()>(JS_ENV_PATCHES, Some("env_patches.js"), settings()))
+        .and_then(|_| ducc.exec::<String>(challenge, Some("challenge.js"), settings()));
+
+    // Duktape reports an interrupted script as an ordinary runtime error.
+    // A script that finished late was not interrupted, so its result still counts.
+    let solution = match result {
+        Err(_) if interrupted.get() => return Err(DecodeError::ChallengeTimeout(limits.timeout)),
+        result => result?,
+    };
+    if solution.len() > limits.max_solution_len {
+        return Err(DecodeError::SolutionTooLarge {
+            len: solution.len(),
+            max: limits.max_solution_len,
+        });
+    }
+
+    Ok(solution)
+}
@@ -0,0 +1,401 @@
+//! Running a challenge in a forked child process with a capped data segment.
+//!
+//! The child runs the challenge, writes a length-prefixed json `Outcome` to a pipe and exits with `_exit`,
+//! so it never returns into the caller's code or runs its destructors.
+
+use super::{
+    run,
+    SandboxLimits,
+};
+use crate::challenge::{
+    DecodeError,
+    SendDuccError,
+    SendDuccErrorKind,
+};
+use ducc::RuntimeErrorCode;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    fs::File,
+    io::{
+        self,
+        Read,
+        Write,
+    },
+    os::unix::io::{
+        AsRawFd,
+        FromRawFd,
+        RawFd,
+    },
+    panic::AssertUnwindSafe,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// How long past the challenge deadline to wait for the child before killing it
+const KILL_GRACE: Duration = Duration::from_secs(1);
+
+/// How often to check whether the child has exited
+const WAIT_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Room for json escapes and error details on top of the longest accepted solution
+const OUTCOME_OVERHEAD: usize = 64 * 1024;
+
+/// The message Duktape throws when an allocation fails
+const ALLOC_FAILED: &str = "alloc failed";
+
+/// Runtime error codes, indexed by their number on the pipe
+const RUNTIME_ERROR_CODES: [RuntimeErrorCode; 7] = [
+    RuntimeErrorCode::Error,
+    RuntimeErrorCode::EvalError,
+    RuntimeErrorCode::RangeError,
+    RuntimeErrorCode::ReferenceError,
+    RuntimeErrorCode::SyntaxError,
+    RuntimeErrorCode::TypeError,
+    RuntimeErrorCode::UriError,
+];
+
+/// What the child reports back
+#[derive(Debug, Serialize, Deserialize)]
+enum Outcome {
+    Solved(String),
+    TimedOut,
+    OutOfMemory,
+    SolutionTooLarge(usize),
+    Failed {
+        kind: ErrorKind,
+        context: Vec<String>,
+    },
+    Other(String),
+}
+
+/// A `SendDuccErrorKind` that can be written to the pipe
+#[derive(Debug, Serialize, Deserialize)]
+enum ErrorKind {
+    ToJsConversion { from: String, to: String },
+    FromJsConversion { from: String, to: String },
+    Runtime { code: usize, name: String },
+    RecursiveMutCallback,
+    External,
+    NotAFunction,
+}
+
+impl Outcome {
+    fn new(result: Result<String, DecodeError>) -> Self {
+        match result {
+            Ok(solution) => Outcome::Solved(solution),
+            Err(DecodeError::ChallengeTimeout(_)) => Outcome::TimedOut,
+            Err(DecodeError::SolutionTooLarge { len, .. }) => Outcome::SolutionTooLarge(len),
+            Err(DecodeError::ChallegeDecode(e)) if is_alloc_failure(&e) => Outcome::OutOfMemory,
+            Err(DecodeError::ChallegeDecode(e)) => Outcome::Failed {
+                kind: e.kind.into(),
+                context: e.context,
+            },
+            Err(e) => Outcome::Other(e.to_string()),
+        }
+    }
+
+    fn into_result(self, limits: &SandboxLimits) -> Result<String, DecodeError> {
+        match self {
+            Outcome::Solved(solution) => Ok(solution),
+            Outcome::TimedOut => Err(DecodeError::ChallengeTimeout(limits.timeout)),
+            Outcome::OutOfMemory => Err(DecodeError::ChallengeMemoryExceeded(limits.max_memory)),
+            Outcome::SolutionTooLarge(len) => Err(DecodeError::SolutionTooLarge {
+                len,
+                max: limits.max_solution_len,
+            }),
+            Outcome::Failed { kind, context } => Err(DecodeError::ChallegeDecode(SendDuccError {
+                kind: kind.into(),
+                context,
+            })),
+            Outcome::Other(e) => Err(DecodeError::SandboxFailed(e)),
+        }
+    }
+}
+
+impl From<SendDuccErrorKind> for ErrorKind {
+    fn from(kind: SendDuccErrorKind) -> Self {
+        match kind {
+            SendDuccErrorKind::ToJsConversionError { from, to } => {
+                ErrorKind::ToJsConversion { from, to }
+            }
+            SendDuccErrorKind::FromJsConversionError { from, to } => {
+                ErrorKind::FromJsConversion { from, to }
+            }
+            SendDuccErrorKind::RuntimeError { code, name } => ErrorKind::Runtime {
+                code: RUNTIME_ERROR_CODES
+                    .iter()
+                    .position(|c| *c == code)
+                    .unwrap_or(0),
+                name,
+            },
+            SendDuccErrorKind::RecursiveMutCallback => ErrorKind::RecursiveMutCallback,
+            SendDuccErrorKind::ExternalError => ErrorKind::External,
+            SendDuccErrorKind::NotAFunction => ErrorKind::NotAFunction,
+        }
+    }
+}
+
+impl From<ErrorKind> for SendDuccErrorKind {
+    fn from(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::ToJsConversion { from, to } => {
+                SendDuccErrorKind::ToJsConversionError { from, to }
+            }
+            ErrorKind::FromJsConversion { from, to } => {
+                SendDuccErrorKind::FromJsConversionError { from, to }
+            }
+            ErrorKind::Runtime { code, name } => SendDuccErrorKind::RuntimeError {
+                code: RUNTIME_ERROR_CODES
+                    .get(code)
+                    .copied()
+                    .unwrap_or(RuntimeErrorCode::Error),
+                name,
+            },
+            ErrorKind::RecursiveMutCallback => SendDuccErrorKind::RecursiveMutCallback,
+            ErrorKind::External => SendDuccErrorKind::ExternalError,
+            ErrorKind::NotAFunction => SendDuccErrorKind::NotAFunction,
+        }
+    }
+}
+
+/// Whether Duktape failed to allocate, which it reports as a `RangeError`
+fn is_alloc_failure(e: &SendDuccError) -> bool {
+    match &e.kind {
+        SendDuccErrorKind::RuntimeError {
+            code: RuntimeErrorCode::RangeError,
+            name,
+        } => name.contains(ALLOC_FAILED) || e.context.iter().any(|c| c.contains(ALLOC_FAILED)),
+        _ => false,
+    }
+}
+
+fn sandbox_error(e: impl std::fmt::Display) -> DecodeError {
+    DecodeError::SandboxFailed(e.to_string())
+}
+
+/// Run a challenge in a child process, capping how much memory it can allocate
+pub(super) fn exec(challenge: &str, limits: &SandboxLimits) -> Result<String, DecodeError> {
+    // The child starts with a copy of this process's data segment, so the cap goes on top of it
+    let data_limit = data_segment_size().map_err(sandbox_error)? + limits.max_memory as u64;
+    let (read_fd, write_fd) = pipe().map_err(sandbox_error)?;
+
+    // Safety: the child only runs the challenge and writes to its end of the pipe, then exits with `_exit`.
+    // It never unwinds or returns into code that expects this process's other threads to exist.
+    let pid = unsafe { libc::fork() };
+    if pid < 0 {
+        let e = io::Error::last_os_error();
+        unsafe {
+            libc::close(read_fd);
+            libc::close(write_fd);
+        }
+        return Err(sandbox_error(e));
+    }
+
+    if pid == 0 {
+        unsafe { libc::close(read_fd) };
+
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            run_child(challenge, limits, data_limit, write_fd)
+        }));
+        let code = match result {
+            Ok(Ok(())) => 0,
+            _ => 1,
+        };
+
+        unsafe { libc::_exit(code) }
+    }
+
+    unsafe { libc::close(write_fd) };
+
+    // Safety: the read end of the pipe is only owned here
+    let mut pipe = unsafe { File::from_raw_fd(read_fd) };
+
+    let deadline = Instant::now() + limits.timeout + KILL_GRACE;
+    let max_len = limits
+        .max_solution_len
+        .saturating_mul(6)
+        .saturating_add(OUTCOME_OVERHEAD);
+    let output = read_outcome(&mut pipe, deadline, max_len);
+
+    // A child that stopped writing gets until the deadline to exit, anything else is killed right away
+    let exit_by = match &output {
+        Ok(Some(_)) => deadline,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => deadline,
+        _ => Instant::now(),
+    };
+    let status = wait_until(pid, exit_by).map_err(sandbox_error)?;
+
+    match output {
+        Ok(Some(output)) => serde_json::from_slice::<Outcome>(&output)
+            .map_err(sandbox_error)?
+            .into_result(limits),
+        Ok(None) => Err(DecodeError::ChallengeTimeout(limits.timeout)),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            Err(DecodeError::SandboxFailed(describe_status(status)))
+        }
+        Err(e) => Err(sandbox_error(e)),
+    }
+}
+
+/// The body of the child process
+fn run_child(
+    challenge: &str,
+    limits: &SandboxLimits,
+    data_limit: u64,
+    write_fd: RawFd,
+) -> io::Result<()> {
+    // Safety: the write end of the pipe is only owned here
+    let mut pipe = unsafe { File::from_raw_fd(write_fd) };
+    set_data_limit(data_limit)?;
+
+    let outcome = serde_json::to_vec(&Outcome::new(run(challenge, limits)))?;
+    pipe.write_all(&(outcome.len() as u64).to_le_bytes())?;
+    pipe.write_all(&outcome)
+}
+
+/// Read the child's length-prefixed outcome, or `None` if the deadline passes first.
+///
+/// The length prefix means this does not wait for the pipe to close,
+/// which could be held open by children forked at the same time on other threads.
+fn read_outcome(pipe: &mut File, deadline: Instant, max_len: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 8];
+    if !read_exact_by(pipe, &mut len, deadline)? {
+        return Ok(None);
+    }
+
+    let len = u64::from_le_bytes(len);
+    if len > max_len as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "challenge outcome is {} bytes, the limit is {}",
+                len, max_len
+            ),
+        ));
+    }
+
+    let mut outcome = vec![0; len as usize];
+    if !read_exact_by(pipe, &mut outcome, deadline)? {
+        return Ok(None);
+    }
+
+    Ok(Some(outcome))
+}
+
+/// Fill the buffer from the pipe, returning false if the deadline passes first
+fn read_exact_by(pipe: &mut File, mut buf: &mut [u8], deadline: Instant) -> io::Result<bool> {
+    while !buf.is_empty() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::from_secs(0) {
+            return Ok(false);
+        }
+
+        let mut poll_fd = libc::pollfd {
+            fd: pipe.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = remaining.as_millis().min(i32::MAX as u128 - 1) as i32 + 1;
+        match unsafe { libc::poll(&mut poll_fd, 1, timeout_ms) } {
+            0 => return Ok(false),
+            n if n < 0 => {
+                let e = io::Error::last_os_error();
+                if e.kind() != io::ErrorKind::Interrupted {
+                    return Err(e);
+                }
+            }
+            _ => match pipe.read(buf) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => buf = &mut buf[n..],
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            },
+        }
+    }
+
+    Ok(true)
+}
+
+/// Make a pipe that is not inherited across `exec`
+fn pipe() -> io::Result<(RawFd, RawFd)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok((fds[0], fds[1]))
+}
+
+/// Wait for a child to exit, killing it if it is still running at the deadline.
+///
+/// Returns the child's wait status.
+fn wait_until(pid: libc::pid_t, deadline: Instant) -> io::Result<i32> {
+    let mut status = 0;
+    let mut options = libc::WNOHANG;
+    loop {
+        match unsafe { libc::waitpid(pid, &mut status, options) } {
+            0 if Instant::now() < deadline => std::thread::sleep(WAIT_INTERVAL),
+            0 => {
+                // Safety: the child has not been reaped, so the pid still refers to it
+                unsafe { libc::kill(pid, libc::SIGKILL) };
+                options = 0;
+            }
+            n if n < 0 => {
+                let e = io::Error::last_os_error();
+                if e.kind() != io::ErrorKind::Interrupted {
+                    return Err(e);
+                }
+            }
+            _ => return Ok(status),
+        }
+    }
+}
+
+fn describe_status(status: i32) -> String {
+    if libc::WIFSIGNALED(status) {
+        format!(
+            "challenge process was killed by signal {}",
+            libc::WTERMSIG(status)
+        )
+    } else {
+        format!(
+            "challenge process exited with status {}",
+            libc::WEXITSTATUS(status)
+        )
+    }
+}
+
+/// The size of this process's data segment, as counted by `RLIMIT_DATA`
+fn data_segment_size() -> io::Result<u64> {
+    let status = std::fs::read_to_string("/proc/self/status")?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmData:"))
+        .and_then(|value| value.trim().strip_suffix("kB"))
+        .and_then(|kb| kb.trim().parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no VmData in /proc/self/status"))
+}
+
+/// Cap the data segment of this process, which covers both `brk` and `mmap` allocations
+fn set_data_limit(limit: u64) -> io::Result<()> {
+    let mut rlimit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(libc::RLIMIT_DATA, &mut rlimit) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    rlimit.rlim_cur = (limit as libc::rlim_t).min(rlimit.rlim_max);
+    if unsafe { libc::setrlimit(libc::RLIMIT_DATA, &rlimit) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
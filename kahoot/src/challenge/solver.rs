@@ -0,0 +1,136 @@
+use super::DecodeError;
+#[cfg(feature = "ducc")]
+use super::SandboxLimits;
+#[cfg(not(feature = "tracing"))]
+use log::trace;
+#[cfg(feature = "tracing")]
+use tracing::trace;
+
+/// A backend that turns a challenge into the string used to decode the session token
+pub trait ChallengeSolver: Send + Sync {
+    /// A short name for the backend, used in logs
+    fn name(&self) -> &str;
+
+    /// Solve a challenge
+    fn solve(&self, challenge: &str) -> Result<String, DecodeError>;
+}
+
+/// Solves challenges by running them in Duktape, in a fresh sandboxed context each time
+#[cfg(feature = "ducc")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DuccSolver {
+    limits: SandboxLimits,
+}
+
+#[cfg(feature = "ducc")]
+impl DuccSolver {
+    /// Make a solver with the default limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the limits challenges run under
+    pub fn limits(mut self, limits: SandboxLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+}
+
+#[cfg(feature = "ducc")]
+impl ChallengeSolver for DuccSolver {
+    fn name(&self) -> &str {
+        "ducc"
+    }
+
+    fn solve(&self, challenge: &str) -> Result<String, DecodeError> {
+        super::sandbox::exec(challenge, &self.limits)
+    }
+}
+
+/// Solves challenges by running them in Boa. Boa currently cannot do this so this will always fail
+#[cfg(feature = "boa")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BoaSolver;
+
+#[cfg(feature = "boa")]
+impl ChallengeSolver for BoaSolver {
+    fn name(&self) -> &str {
+        "boa"
+    }
+
+    fn solve(&self, challenge: &str) -> Result<String, DecodeError> {
+        super::decode_challenge_boa(challenge).map_err(DecodeError::ChallegeDecodeBoa)
+    }
+}
+
+/// Solves challenges by parsing their expected shape, without a js engine
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NativeSolver;
+
+impl ChallengeSolver for NativeSolver {
+    fn name(&self) -> &str {
+        "native"
+    }
+
+    fn solve(&self, challenge: &str) -> Result<String, DecodeError> {
+        Ok(super::decode_challenge_native(challenge)?)
+    }
+}
+
+/// Tries each solver in order until one succeeds
+#[derive(Default)]
+pub struct FallbackSolver {
+    solvers: Vec<Box<dyn ChallengeSolver>>,
+}
+
+impl FallbackSolver {
+    /// Make an empty chain
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a solver to the end of the chain
+    pub fn with<S: ChallengeSolver + 'static>(mut self, solver: S) -> Self {
+        self.solvers.push(Box::new(solver));
+        self
+    }
+
+    /// The solvers in the chain
+    pub fn solvers(&self) -> &[Box<dyn ChallengeSolver>] {
+        &self.solvers
+    }
+}
+
+impl ChallengeSolver for FallbackSolver {
+    fn name(&self) -> &str {
+        "fallback"
+    }
+
+    fn solve(&self, challenge: &str) -> Result<String, DecodeError> {
+        let mut errors = Vec::with_capacity(self.solvers.len());
+        for solver in self.solvers.iter() {
+            match solver.solve(challenge) {
+                Ok(solved) => return Ok(solved),
+                Err(e) => {
+                    trace!("solver '{}' failed: {}", solver.name(), e);
+                    errors.push(e);
+                }
+            }
+        }
+
+        Err(DecodeError::AllSolversFailed(errors))
+    }
+}
+
+/// The default solver: the native solver, falling back to every enabled js engine
+pub fn default_solver() -> FallbackSolver {
+    let solver = FallbackSolver::new().with(NativeSolver);
+
+    #[cfg(feature = "ducc")]
+    let solver = solver.with(DuccSolver::new());
+
+    #[cfg(feature = "boa")]
+    let solver = solver.with(BoaSolver);
+
+    solver
+}
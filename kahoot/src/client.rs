@@ -1,58 +1,127 @@
+mod builder;
 mod handler;
 
-pub use self::handler::{
-    DefaultHandler,
-    Handler,
+use self::builder::Settings;
+pub use self::{
+    builder::{
+        ClientBuilder,
+        DeviceProfile,
+        LagSource,
+    },
+    handler::{
+        DefaultHandler,
+        ErrorPolicy,
+        Fallible,
+        Handler,
+        TryHandler,
+    },
 };
 use crate::{
     async_trait,
+    challenge::ChallengeSolver,
+    Answer,
+    Endpoints,
+    GameState,
     KahootError,
     KahootResult,
+    LoginRejection,
     LoginResponse,
     Message,
-    USER_AGENT_STR,
 };
 use cometd::{
     json,
     packet::Packet,
     CometError,
 };
+#[cfg(not(feature = "tracing"))]
 use log::{
     trace,
     warn,
 };
-use std::sync::{
-    Arc,
-    Mutex,
+use std::{
+    future::Future,
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::Duration,
+};
+#[cfg(feature = "tracing")]
+use tracing::{
+    trace,
+    warn,
 };
 
 pub const DEFAULT_DEVICE_WIDTH: u64 = 1920;
 pub const DEFAULT_DEVICE_HEIGHT: u64 = 1080;
 pub const DEFAULT_LAG: u64 = 0;
+pub const DEFAULT_RELOGIN_DELAY: Duration = Duration::from_millis(1000);
+pub const DEFAULT_JOIN_TIMEOUT: Duration = Duration::from_secs(10);
 pub const CONTROLLER_CHANNEL: &str = "/service/controller";
 pub const PLAYER_CHANNEL: &str = "/service/player";
 pub const STATUS_CHANNEL: &str = "/service/status";
 
+/// Spawn a handler task.
+///
+/// With the `tracing` feature, the task runs in the span that spawned it.
+fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    #[cfg(feature = "tracing")]
+    let future = tracing::Instrument::in_current_span(future);
+
+    tokio::spawn(future);
+}
+
 pub(crate) struct KahootHandler<T> {
     pub(crate) code: Arc<str>,
-    pub(crate) name: Arc<str>,
+
+    /// The name the game accepted, or the requested name until it accepts one
+    pub(crate) name: Arc<Mutex<Arc<str>>>,
     pub(crate) handler: Arc<T>,
+    pub(crate) endpoints: Arc<Endpoints>,
+
+    pub(crate) settings: Arc<Settings>,
+
+    /// Whether players join in teams
+    pub(crate) team_mode: bool,
 
     pub(crate) exit_error: Arc<Mutex<Option<KahootError>>>,
+    pub(crate) state: Arc<Mutex<GameState>>,
 }
 
 impl<T> KahootHandler<T> {
-    pub(crate) fn new(code: &str, name: &str, handler: T) -> Self {
+    pub(crate) fn new(
+        code: &str,
+        name: &str,
+        handler: T,
+        endpoints: Endpoints,
+        settings: Settings,
+        team_mode: bool,
+    ) -> Self {
         Self {
             code: Arc::from(code),
-            name: Arc::from(name),
+            name: Arc::new(Mutex::new(Arc::from(name))),
             handler: Arc::new(handler),
+            endpoints: Arc::new(endpoints),
+            settings: Arc::new(settings),
+            team_mode,
             exit_error: Arc::new(Mutex::new(None)),
+            state: Arc::new(Mutex::new(GameState::default())),
         }
     }
 
     pub(crate) fn kahoot_ctx(&self, ctx: &cometd::client::Context) -> Context {
-        Context::new(ctx.clone(), self.code.clone(), self.name.clone())
+        Context {
+            ctx: ctx.clone(),
+            code: self.code.clone(),
+            name: self.name.clone(),
+            endpoints: self.endpoints.clone(),
+            settings: self.settings.clone(),
+            exit_error: self.exit_error.clone(),
+            state: self.state.clone(),
+        }
     }
 }
 
@@ -60,21 +129,35 @@ impl<T> KahootHandler<T> {
 pub struct Context {
     pub ctx: cometd::client::Context,
     pub code: Arc<str>,
-    pub name: Arc<str>,
+    pub endpoints: Arc<Endpoints>,
+
+    name: Arc<Mutex<Arc<str>>>,
+    settings: Arc<Settings>,
+    exit_error: Arc<Mutex<Option<KahootError>>>,
+    state: Arc<Mutex<GameState>>,
 }
 
 impl Context {
     pub fn new(ctx: cometd::client::Context, code: Arc<str>, name: Arc<str>) -> Self {
-        Context { ctx, code, name }
+        Context {
+            ctx,
+            code,
+            name: Arc::new(Mutex::new(name)),
+            endpoints: Arc::new(Endpoints::default()),
+            settings: Arc::new(Settings::default()),
+            exit_error: Arc::new(Mutex::new(None)),
+            state: Arc::new(Mutex::new(GameState::default())),
+        }
     }
 
     pub fn get_device_data_str(&self) -> KahootResult<String> {
+        let device = &self.settings.device;
         let content = json!({
             "device": {
-                "userAgent": USER_AGENT_STR,
+                "userAgent": device.user_agent,
                 "screen": {
-                    "width": DEFAULT_DEVICE_WIDTH,
-                    "height": DEFAULT_DEVICE_HEIGHT,
+                    "width": device.width,
+                    "height": device.height,
                 }
             }
         });
@@ -82,8 +165,11 @@ impl Context {
         Ok(serde_json::to_string(&content)?)
     }
 
-    /// Login to kahoot
-    pub async fn login(&self, name: &str) -> KahootResult<()> {
+    /// Login to kahoot, waiting for the server to accept or reject the name.
+    ///
+    /// A rejected login is returned as a `KahootError::InvalidLogin`,
+    /// and a login that is not answered within the join timeout as a `KahootError::JoinTimeout`.
+    pub async fn login(&self, name: &str) -> KahootResult<LoginResponse> {
         trace!("logging in as '{}'", name);
 
         let client_id = self
@@ -91,60 +177,150 @@ impl Context {
             .get_client_id()
             .ok_or(KahootError::Comet(CometError::MissingClientId))?;
 
+        self.ctx.subscribe(CONTROLLER_CHANNEL).await?;
+        self.ctx.subscribe(PLAYER_CHANNEL).await?;
+        self.ctx.subscribe(STATUS_CHANNEL).await?;
+
         let packet = Packet::new()
             .channel(CONTROLLER_CHANNEL.into())
             .client_id(client_id)
             .data(json!({
                 "type": "login",
                 "gameid": &*self.code,
-                "host": "kahoot.it",
+                "host": &self.endpoints.host,
                 "name": name,
                 "content": self.get_device_data_str()?,
             }));
 
-        self.ctx.send_packet(packet).await?;
-
-        self.ctx.subscribe(CONTROLLER_CHANNEL).await?;
-        self.ctx.subscribe(PLAYER_CHANNEL).await?;
-        self.ctx.subscribe(STATUS_CHANNEL).await?;
+        let reply = self
+            .ctx
+            .request(
+                packet,
+                CONTROLLER_CHANNEL,
+                |packet| {
+                    packet
+                        .data
+                        .as_ref()
+                        .and_then(LoginResponse::from_value)
+                        .is_some()
+                },
+                self.settings.join_timeout,
+            )
+            .await
+            .map_err(|e| match e {
+                CometError::Timeout(timeout) => KahootError::JoinTimeout(timeout),
+                e => KahootError::Comet(e),
+            })?;
+
+        let response = reply
+            .data
+            .as_ref()
+            .and_then(LoginResponse::from_value)
+            .expect("login reply matched as a login response");
+
+        if response.error.is_some() {
+            return Err(KahootError::InvalidLogin(LoginRejection::from_response(
+                response,
+            )));
+        }
 
-        Ok(())
+        Ok(response)
     }
 
-    /// Submit an answer
-    pub async fn submit_answer(&self, choice: usize) -> KahootResult<()> {
+    /// Submit the names of the members of this player's team, in team mode games
+    pub async fn submit_team(&self, members: &[String]) -> KahootResult<()> {
         let client_id = self
             .ctx
             .get_client_id()
             .ok_or(KahootError::Comet(CometError::MissingClientId))?;
 
+        let packet = Packet::new()
+            .channel(CONTROLLER_CHANNEL.into())
+            .client_id(client_id)
+            .data(json!({
+                "content": serde_json::to_string(members)?,
+                "gameid": &*self.code,
+                "host": &self.endpoints.host,
+                "id": 18,
+                "type": "message",
+            }));
+
+        self.ctx.send_packet(packet).await?;
+
+        Ok(())
+    }
+
+    /// Submit an answer
+    pub async fn submit_answer(&self, choice: usize) -> KahootResult<()> {
         let content = json!({
             "choice": choice,
-            "meta": {
-                "lag": DEFAULT_LAG,
-                "device": self.get_device_data_str()?,
-            }
+            "meta": self.get_answer_meta()?,
         });
 
+        self.send_answer_content(content).await
+    }
+
+    /// Submit an answer to the current question.
+    ///
+    /// The answer must fit the question's block type and choices.
+    pub async fn submit(&self, answer: Answer) -> KahootResult<()> {
+        let state = self.game_state();
+        let (question_index, block_type) = match (state.question_index, &state.block_type) {
+            (Some(question_index), Some(block_type)) => (question_index, block_type),
+            _ => return Err(KahootError::NoQuestion),
+        };
+        answer.validate(block_type, state.answer_count())?;
+
+        let mut content = answer.content(block_type, question_index);
+        content["meta"] = self.get_answer_meta()?;
+
+        self.send_answer_content(content).await
+    }
+
+    /// Get a copy of the current game state
+    pub fn game_state(&self) -> GameState {
+        self.state.lock().unwrap().clone()
+    }
+
+    fn get_answer_meta(&self) -> KahootResult<serde_json::Value> {
+        Ok(json!({
+            "lag": (self.settings.lag)(),
+            "device": self.get_device_data_str()?,
+        }))
+    }
+
+    async fn send_answer_content(&self, content: serde_json::Value) -> KahootResult<()> {
+        let client_id = self
+            .ctx
+            .get_client_id()
+            .ok_or(KahootError::Comet(CometError::MissingClientId))?;
+
         let packet = Packet::new()
             .channel(CONTROLLER_CHANNEL.into())
             .client_id(client_id)
             .data(json!({
                 "content": serde_json::to_string(&content)?,
                 "gameid": &*self.code,
-                "host": "kahoot.it",
+                "host": &self.endpoints.host,
                 "id": 45,
                 "type": "message",
             }));
 
         self.ctx.send_packet(packet).await?;
+        self.state.lock().unwrap().answered = true;
 
         Ok(())
     }
 
-    /// Get the username
+    /// Get the username.
+    ///
+    /// This is the name the game accepted, which differs from the requested name in namerator games.
     pub fn get_username(&self) -> Arc<str> {
-        self.name.clone()
+        self.name.lock().unwrap().clone()
+    }
+
+    fn set_username(&self, name: &str) {
+        *self.name.lock().unwrap() = Arc::from(name);
     }
 
     /// Try to shutdown the client
@@ -153,21 +329,52 @@ impl Context {
 
         Ok(())
     }
+
+    /// Stop the client, making `Client::run` return the given error.
+    ///
+    /// If the client is already being stopped with an error, the first error is kept.
+    pub fn stop_with_error(&self, error: KahootError) {
+        {
+            let mut exit_error = self.exit_error.lock().unwrap();
+            if exit_error.is_none() {
+                *exit_error = Some(error);
+            }
+        }
+
+        self.ctx.cancel_handle().cancel();
+    }
 }
 
 #[async_trait]
 impl<T: Handler + 'static> cometd::client::Handler for KahootHandler<T> {
-    async fn on_reconnect(&self, ctx: cometd::client::Context) {
+    async fn on_reconnect(&self, ctx: cometd::client::Context, resumed: bool) {
+        // The server still knows who we are, so we are still logged in.
+        if resumed {
+            trace!("resumed session");
+            return;
+        }
+
         let ctx = self.kahoot_ctx(&ctx);
 
         let handler = self.handler.clone();
-        let name = self.name.clone();
-        tokio::spawn(async move {
+        let team_mode = self.team_mode;
+        let relogin_delay = self.settings.relogin_delay;
+        spawn(async move {
             // Needs to satisfy 500 < x < 1000. Upper bound is optional (within reason).
-            tokio::time::delay_for(std::time::Duration::from_millis(1000)).await;
+            tokio::time::delay_for(relogin_delay).await;
+
+            let name = ctx.get_username();
             match ctx.login(&name).await {
-                Ok(_) => {}
-                Err(e) => handler.on_error(ctx, e).await,
+                Ok(response) => {
+                    if team_mode || response.team_mode() {
+                        let members = handler.on_team_members(ctx.clone(), name.to_string()).await;
+                        if let Err(e) = ctx.submit_team(&members).await {
+                            handler.on_error(ctx, e).await;
+                        }
+                    }
+                }
+                // Without a login the client is useless, so stop instead of idling
+                Err(e) => ctx.stop_with_error(e),
             }
         });
     }
@@ -181,22 +388,26 @@ impl<T: Handler + 'static> cometd::client::Handler for KahootHandler<T> {
                 };
 
                 if let Some(login_response) = LoginResponse::from_value(data) {
+                    // A rejected login is reported by the login call
                     if login_response.error.as_ref().is_some() {
-                        *self.exit_error.lock().unwrap() =
-                            Some(KahootError::InvalidLogin(login_response));
-                        ctx.shutdown().await.expect("Shutdown");
+                        trace!("login rejected: {:?}", login_response);
                     } else {
                         let handler = self.handler.clone();
                         let ctx = self.kahoot_ctx(&ctx);
 
-                        tokio::spawn(async move { handler.on_login(ctx).await });
+                        spawn(async move { handler.on_login(ctx).await });
                     }
                 } else {
-                    warn!("Controller Packet: {:#?}", packet);
+                    warn!("unhandled controller message: {}", data);
                 }
             }
             STATUS_CHANNEL => {
-                warn!("Status Packet: {:#?}", packet);
+                let data = match packet.data.as_ref() {
+                    Some(d) => d,
+                    None => &serde_json::Value::Null,
+                };
+
+                warn!("unhandled status message: {}", data);
             }
             PLAYER_CHANNEL => {
                 let data = match packet.data {
@@ -204,34 +415,143 @@ impl<T: Handler + 'static> cometd::client::Handler for KahootHandler<T> {
                     None => serde_json::Value::Null,
                 };
 
-                match Message::from_value(data) {
+                let message = Message::from_value(data);
+                self.state.lock().unwrap().apply(&message);
+
+                match message {
                     Message::UsernameAccepted { msg, .. } => {
                         let handler = self.handler.clone();
                         let ctx = self.kahoot_ctx(&ctx);
 
-                        tokio::spawn(async move { handler.on_username_accepted(ctx, msg).await });
+                        // Set before later messages are handled, so they see the accepted name
+                        ctx.set_username(&msg.player_name);
+                        spawn(async move {
+                            handler
+                                .on_name_assigned(ctx.clone(), msg.player_name.clone())
+                                .await;
+                            handler.on_username_accepted(ctx, msg).await
+                        });
                     }
                     Message::GetReady { msg } => {
                         let handler = self.handler.clone();
                         let ctx = self.kahoot_ctx(&ctx);
 
-                        tokio::spawn(async move { handler.on_get_ready(ctx, msg).await });
+                        spawn(async move { handler.on_get_ready(ctx, msg).await });
                     }
                     Message::StartQuestion { msg } => {
                         let ctx = self.kahoot_ctx(&ctx);
                         let handler = self.handler.clone();
 
-                        tokio::spawn(async move {
+                        spawn(async move {
                             handler.on_start_question(ctx, msg).await;
                         });
                     }
-                    msg => {
-                        warn!("Unknown Message: {:#?}", msg);
+                    Message::TimeUp { msg } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_time_up(ctx, msg).await });
+                    }
+                    Message::RevealAnswer { msg } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_reveal_answer(ctx, msg).await });
+                    }
+                    Message::StartQuiz { msg } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_start_quiz(ctx, msg).await });
+                    }
+                    Message::Feedback { msg } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_feedback(ctx, msg).await });
+                    }
+                    Message::RevealRanking { msg, .. } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_reveal_ranking(ctx, msg).await });
+                    }
+                    Message::GameOver { msg, .. } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_game_over(ctx, msg).await });
+                    }
+                    Message::PlayAgain { msg } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_play_again(ctx, msg).await });
+                    }
+                    Message::AnswerSelected { msg } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_answer_selected(ctx, msg).await });
+                    }
+                    Message::AnswerResponse { msg } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_answer_response(ctx, msg).await });
+                    }
+                    Message::ResetController { msg } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_reset_controller(ctx, msg).await });
+                    }
+                    Message::SubmitFeedback { msg } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_submit_feedback(ctx, msg).await });
+                    }
+                    Message::UsernameRejected { msg } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_username_rejected(ctx, msg).await });
+                    }
+                    Message::GameBlockStart { msg } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_game_block_start(ctx, msg).await });
+                    }
+                    Message::GameBlockEnd { msg } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_game_block_end(ctx, msg).await });
+                    }
+                    Message::GameBlockAnswer { msg } => {
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_game_block_answer(ctx, msg).await });
+                    }
+                    Message::Unknown(value) => {
+                        trace!("unknown player message: {}", value);
+
+                        let handler = self.handler.clone();
+                        let ctx = self.kahoot_ctx(&ctx);
+
+                        spawn(async move { handler.on_unknown_message(ctx, value).await });
                     }
                 }
             }
             _ => {
-                warn!("Unknown Packet: {:#?}", packet);
+                warn!(
+                    "unknown packet on channel '{}': {:?}",
+                    packet.channel.as_str(),
+                    packet.data
+                );
             }
         }
     }
@@ -240,7 +560,7 @@ impl<T: Handler + 'static> cometd::client::Handler for KahootHandler<T> {
         let handler = self.handler.clone();
         let ctx = self.kahoot_ctx(&ctx);
 
-        tokio::spawn(async move {
+        spawn(async move {
             let _result = handler.on_error(ctx, error.into()).await;
         });
     }
@@ -252,6 +572,7 @@ pub struct Client<T> {
 
 impl<T: Handler + Send + 'static> Client<T> {
     /// Connect with the given handler
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(handler)))]
     pub async fn connect_with_handler(
         code: String,
         name: String,
@@ -263,19 +584,45 @@ impl<T: Handler + Send + 'static> Client<T> {
             name
         );
 
-        let token = crate::challenge::Client::new().get_token(&code).await?;
-
-        trace!("solved challenge, got token='{}'", token);
+        ClientBuilder::new(code, name).connect(handler).await
+    }
 
-        let url = format!("wss://kahoot.it/cometd/{}/{}", &code, token);
-        let handler = KahootHandler::new(&code, &name, handler);
-        let client = cometd::Client::connect_with_handler(&url, handler).await?;
-        let client = Client { client };
+    /// Connect with the given handler, solving the join challenge with the given solver
+    pub async fn connect_with_solver<S: ChallengeSolver + 'static>(
+        code: String,
+        name: String,
+        handler: T,
+        solver: S,
+    ) -> KahootResult<Client<T>> {
+        ClientBuilder::new(code, name)
+            .challenge_client(crate::challenge::Client::with_solver(solver))
+            .connect(handler)
+            .await
+    }
 
-        Ok(client)
+    /// Connect with the given handler, probing and solving the join challenge with the given challenge client.
+    ///
+    /// The challenge client's endpoints are used for the whole connection.
+    pub async fn connect_with_challenge_client(
+        code: String,
+        name: String,
+        handler: T,
+        challenge_client: crate::challenge::Client,
+    ) -> KahootResult<Client<T>> {
+        ClientBuilder::new(code, name)
+            .challenge_client(challenge_client)
+            .connect(handler)
+            .await
     }
 
     /// Run the client
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip(self),
+            fields(code = %self.client.handler.code, name = %self.client.handler.name.lock().unwrap()),
+        )
+    )]
     pub async fn run(&mut self) -> KahootResult<()> {
         trace!("running kahoot client");
 
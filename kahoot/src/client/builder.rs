@@ -0,0 +1,191 @@
+use super::{
+    Client,
+    Handler,
+    KahootHandler,
+    DEFAULT_DEVICE_HEIGHT,
+    DEFAULT_DEVICE_WIDTH,
+    DEFAULT_JOIN_TIMEOUT,
+    DEFAULT_LAG,
+    DEFAULT_RELOGIN_DELAY,
+};
+use crate::{
+    Endpoints,
+    KahootResult,
+    USER_AGENT_STR,
+};
+#[cfg(not(feature = "tracing"))]
+use log::trace;
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+#[cfg(feature = "tracing")]
+use tracing::trace;
+
+/// Produces the lag reported with each answer, in milliseconds
+pub type LagSource = Arc<dyn Fn() -> u64 + Send + Sync>;
+
+/// The device a client claims to be
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceProfile {
+    /// The browser user agent
+    pub user_agent: String,
+
+    /// The screen width, in pixels
+    pub width: u64,
+
+    /// The screen height, in pixels
+    pub height: u64,
+}
+
+impl DeviceProfile {
+    /// A desktop chrome browser on a 1080p screen
+    pub fn new() -> Self {
+        Self {
+            user_agent: USER_AGENT_STR.to_string(),
+            width: DEFAULT_DEVICE_WIDTH,
+            height: DEFAULT_DEVICE_HEIGHT,
+        }
+    }
+
+    /// Set the user agent
+    pub fn user_agent(mut self, user_agent: String) -> Self {
+        self.user_agent = user_agent;
+        self
+    }
+
+    /// Set the screen size
+    pub fn screen(mut self, width: u64, height: u64) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+}
+
+impl Default for DeviceProfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Player settings shared by a client's contexts
+#[derive(Clone)]
+pub(crate) struct Settings {
+    pub(crate) device: DeviceProfile,
+    pub(crate) lag: LagSource,
+    pub(crate) relogin_delay: Duration,
+    pub(crate) join_timeout: Duration,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            device: DeviceProfile::default(),
+            lag: Arc::new(|| DEFAULT_LAG),
+            relogin_delay: DEFAULT_RELOGIN_DELAY,
+            join_timeout: DEFAULT_JOIN_TIMEOUT,
+        }
+    }
+}
+
+/// Configures and connects a `kahoot::Client`
+pub struct ClientBuilder {
+    code: String,
+    name: String,
+    settings: Settings,
+    challenge_client: Option<crate::challenge::Client>,
+    endpoints: Option<Endpoints>,
+}
+
+impl ClientBuilder {
+    /// Make a builder for a player with the given name joining the game with the given code
+    pub fn new(code: String, name: String) -> Self {
+        Self {
+            code,
+            name,
+            settings: Settings::default(),
+            challenge_client: None,
+            endpoints: None,
+        }
+    }
+
+    /// Set the device the client claims to be
+    pub fn device(mut self, device: DeviceProfile) -> Self {
+        self.settings.device = device;
+        self
+    }
+
+    /// Report a fixed lag, in milliseconds, with each answer
+    pub fn lag(self, lag: u64) -> Self {
+        self.lag_source(move || lag)
+    }
+
+    /// Set where the lag reported with each answer comes from.
+    ///
+    /// The source is called once per answer.
+    pub fn lag_source<F>(mut self, lag_source: F) -> Self
+    where
+        F: Fn() -> u64 + Send + Sync + 'static,
+    {
+        self.settings.lag = Arc::new(lag_source);
+        self
+    }
+
+    /// Set how long to wait after a new session is made before logging in.
+    ///
+    /// Kahoot rejects logins that come too soon; it seems to need more than 500 ms.
+    pub fn relogin_delay(mut self, relogin_delay: Duration) -> Self {
+        self.settings.relogin_delay = relogin_delay;
+        self
+    }
+
+    /// Set the challenge client to solve the join challenge with.
+    ///
+    /// A clone of one challenge client can be given to many builders to share its connection pool.
+    pub fn challenge_client(mut self, challenge_client: crate::challenge::Client) -> Self {
+        self.challenge_client = Some(challenge_client);
+        self
+    }
+
+    /// Set the endpoints to connect to, replacing the challenge client's endpoints
+    pub fn endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = Some(endpoints);
+        self
+    }
+
+    /// Set how long to wait for the server to answer the login.
+    ///
+    /// Probing the code, solving the challenge and connecting are not covered by this timeout.
+    pub fn join_timeout(mut self, join_timeout: Duration) -> Self {
+        self.settings.join_timeout = join_timeout;
+        self
+    }
+
+    /// Connect with the given handler
+    pub async fn connect<T: Handler + 'static>(self, handler: T) -> KahootResult<Client<T>> {
+        let mut challenge_client = self.challenge_client.unwrap_or_default();
+        if let Some(endpoints) = self.endpoints {
+            challenge_client = challenge_client.endpoints(endpoints);
+        }
+
+        let probe = challenge_client.probe_code(&self.code).await?;
+        let team_mode = probe.session_info().team_mode();
+        let token = challenge_client.solve(probe).await?;
+
+        trace!("solved challenge, got token='{}'", token);
+
+        let endpoints = challenge_client.get_endpoints().clone();
+        let url = endpoints.cometd_session_url(&self.code, &token);
+        let handler = KahootHandler::new(
+            &self.code,
+            &self.name,
+            handler,
+            endpoints,
+            self.settings,
+            team_mode,
+        );
+        let client = cometd::Client::connect_with_handler(&url, handler).await?;
+
+        Ok(Client { client })
+    }
+}
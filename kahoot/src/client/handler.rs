@@ -2,18 +2,70 @@ use crate::{
     client::Context,
     error::KahootError,
     message::{
+        AnswerResponseMessage,
+        AnswerSelectedMessage,
+        FeedbackMessage,
+        GameBlockAnswerMessage,
+        GameBlockEndMessage,
+        GameBlockStartMessage,
+        GameOverMessage,
         GetReadyMessage,
+        PlayAgainMessage,
+        ResetControllerMessage,
+        RevealAnswerMessage,
+        RevealRankingMessage,
         StartQuestionMessage,
+        StartQuizMessage,
+        SubmitFeedbackMessage,
+        TimeUpMessage,
         UsernameAcceptedMessage,
+        UsernameRejectedMessage,
     },
+    KahootResult,
 };
+pub use cometd::client::ErrorPolicy;
+#[cfg(not(feature = "tracing"))]
+use log::warn;
+#[cfg(feature = "tracing")]
+use tracing::warn;
 
 #[crate::async_trait]
 pub trait Handler: Send + Sync {
     async fn on_login(&self, _ctx: Context) {}
+
+    /// Called with the name the game accepted the player under.
+    ///
+    /// Namerator games pick this name themselves, ignoring the requested one.
+    async fn on_name_assigned(&self, _ctx: Context, _name: String) {}
+
+    /// Called after logging in to a team mode game, to get the names of the team's members.
+    ///
+    /// By default, the team is just the player.
+    async fn on_team_members(&self, _ctx: Context, name: String) -> Vec<String> {
+        vec![name]
+    }
+
     async fn on_username_accepted(&self, _ctx: Context, _msg: UsernameAcceptedMessage) {}
     async fn on_get_ready(&self, _ctx: Context, _msg: GetReadyMessage) {}
     async fn on_start_question(&self, _ctx: Context, _msg: StartQuestionMessage) {}
+    async fn on_time_up(&self, _ctx: Context, _msg: TimeUpMessage) {}
+    async fn on_reveal_answer(&self, _ctx: Context, _msg: Box<RevealAnswerMessage>) {}
+    async fn on_start_quiz(&self, _ctx: Context, _msg: StartQuizMessage) {}
+    async fn on_feedback(&self, _ctx: Context, _msg: FeedbackMessage) {}
+    async fn on_reveal_ranking(&self, _ctx: Context, _msg: RevealRankingMessage) {}
+    async fn on_game_over(&self, _ctx: Context, _msg: Box<GameOverMessage>) {}
+    async fn on_play_again(&self, _ctx: Context, _msg: PlayAgainMessage) {}
+    async fn on_answer_selected(&self, _ctx: Context, _msg: AnswerSelectedMessage) {}
+    async fn on_answer_response(&self, _ctx: Context, _msg: AnswerResponseMessage) {}
+    async fn on_reset_controller(&self, _ctx: Context, _msg: ResetControllerMessage) {}
+    async fn on_submit_feedback(&self, _ctx: Context, _msg: SubmitFeedbackMessage) {}
+    async fn on_username_rejected(&self, _ctx: Context, _msg: UsernameRejectedMessage) {}
+    async fn on_game_block_start(&self, _ctx: Context, _msg: GameBlockStartMessage) {}
+    async fn on_game_block_end(&self, _ctx: Context, _msg: GameBlockEndMessage) {}
+    async fn on_game_block_answer(&self, _ctx: Context, _msg: GameBlockAnswerMessage) {}
+
+    /// Called with the raw json of a player message that could not be parsed
+    async fn on_unknown_message(&self, _ctx: Context, _value: serde_json::Value) {}
 
     async fn on_error(&self, _ctx: Context, _e: KahootError) {}
 }
@@ -22,3 +74,309 @@ pub struct DefaultHandler;
 
 #[crate::async_trait]
 impl Handler for DefaultHandler {}
+
+/// A handler whose callbacks can fail.
+///
+/// Wrap it in a `Fallible` to use it as a `Handler`; the `ErrorPolicy` decides what happens to its errors.
+#[crate::async_trait]
+pub trait TryHandler: Send + Sync {
+    async fn on_login(&self, _ctx: Context) -> KahootResult<()> {
+        Ok(())
+    }
+
+    /// Called with the name the game accepted the player under.
+    ///
+    /// Namerator games pick this name themselves, ignoring the requested one.
+    async fn on_name_assigned(&self, _ctx: Context, _name: String) -> KahootResult<()> {
+        Ok(())
+    }
+
+    /// Called after logging in to a team mode game, to get the names of the team's members.
+    ///
+    /// By default, the team is just the player. A failed callback also submits just the player.
+    async fn on_team_members(&self, _ctx: Context, name: String) -> KahootResult<Vec<String>> {
+        Ok(vec![name])
+    }
+
+    async fn on_username_accepted(
+        &self,
+        _ctx: Context,
+        _msg: UsernameAcceptedMessage,
+    ) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_get_ready(&self, _ctx: Context, _msg: GetReadyMessage) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_start_question(
+        &self,
+        _ctx: Context,
+        _msg: StartQuestionMessage,
+    ) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_time_up(&self, _ctx: Context, _msg: TimeUpMessage) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_reveal_answer(
+        &self,
+        _ctx: Context,
+        _msg: Box<RevealAnswerMessage>,
+    ) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_start_quiz(&self, _ctx: Context, _msg: StartQuizMessage) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_feedback(&self, _ctx: Context, _msg: FeedbackMessage) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_reveal_ranking(
+        &self,
+        _ctx: Context,
+        _msg: RevealRankingMessage,
+    ) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_game_over(&self, _ctx: Context, _msg: Box<GameOverMessage>) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_play_again(&self, _ctx: Context, _msg: PlayAgainMessage) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_answer_selected(
+        &self,
+        _ctx: Context,
+        _msg: AnswerSelectedMessage,
+    ) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_answer_response(
+        &self,
+        _ctx: Context,
+        _msg: AnswerResponseMessage,
+    ) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_reset_controller(
+        &self,
+        _ctx: Context,
+        _msg: ResetControllerMessage,
+    ) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_submit_feedback(
+        &self,
+        _ctx: Context,
+        _msg: SubmitFeedbackMessage,
+    ) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_username_rejected(
+        &self,
+        _ctx: Context,
+        _msg: UsernameRejectedMessage,
+    ) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_game_block_start(
+        &self,
+        _ctx: Context,
+        _msg: GameBlockStartMessage,
+    ) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_game_block_end(
+        &self,
+        _ctx: Context,
+        _msg: GameBlockEndMessage,
+    ) -> KahootResult<()> {
+        Ok(())
+    }
+    async fn on_game_block_answer(
+        &self,
+        _ctx: Context,
+        _msg: GameBlockAnswerMessage,
+    ) -> KahootResult<()> {
+        Ok(())
+    }
+
+    /// Called with the raw json of a player message that could not be parsed
+    async fn on_unknown_message(
+        &self,
+        _ctx: Context,
+        _value: serde_json::Value,
+    ) -> KahootResult<()> {
+        Ok(())
+    }
+
+    async fn on_error(&self, _ctx: Context, _e: KahootError) {}
+}
+
+/// Adapts a `TryHandler` into a `Handler`, applying an `ErrorPolicy` to its errors
+pub struct Fallible<T> {
+    handler: T,
+    policy: ErrorPolicy,
+}
+
+impl<T: TryHandler> Fallible<T> {
+    /// Wrap a handler, forwarding its errors to its `on_error`
+    pub fn new(handler: T) -> Self {
+        Self {
+            handler,
+            policy: ErrorPolicy::ForwardToOnError,
+        }
+    }
+
+    /// Set the error policy
+    pub fn policy(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Get the wrapped handler
+    pub fn handler(&self) -> &T {
+        &self.handler
+    }
+
+    async fn handle_result(&self, ctx: Context, result: KahootResult<()>) {
+        let e = match result {
+            Ok(()) => return,
+            Err(e) => e,
+        };
+
+        ctx.ctx.record_handler_error();
+
+        match self.policy {
+            ErrorPolicy::Log => warn!("handler error: {}", e),
+            ErrorPolicy::ForwardToOnError => self.handler.on_error(ctx, e).await,
+            ErrorPolicy::Stop => ctx.stop_with_error(e),
+        }
+    }
+}
+
+#[crate::async_trait]
+impl<T: TryHandler> Handler for Fallible<T> {
+    async fn on_login(&self, ctx: Context) {
+        let result = self.handler.on_login(ctx.clone()).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_name_assigned(&self, ctx: Context, name: String) {
+        let result = self.handler.on_name_assigned(ctx.clone(), name).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_team_members(&self, ctx: Context, name: String) -> Vec<String> {
+        match self
+            .handler
+            .on_team_members(ctx.clone(), name.clone())
+            .await
+        {
+            Ok(members) => members,
+            Err(e) => {
+                self.handle_result(ctx, Err(e)).await;
+                vec![name]
+            }
+        }
+    }
+
+    async fn on_username_accepted(&self, ctx: Context, msg: UsernameAcceptedMessage) {
+        let result = self.handler.on_username_accepted(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_get_ready(&self, ctx: Context, msg: GetReadyMessage) {
+        let result = self.handler.on_get_ready(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_start_question(&self, ctx: Context, msg: StartQuestionMessage) {
+        let result = self.handler.on_start_question(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_time_up(&self, ctx: Context, msg: TimeUpMessage) {
+        let result = self.handler.on_time_up(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_reveal_answer(&self, ctx: Context, msg: Box<RevealAnswerMessage>) {
+        let result = self.handler.on_reveal_answer(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_start_quiz(&self, ctx: Context, msg: StartQuizMessage) {
+        let result = self.handler.on_start_quiz(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_feedback(&self, ctx: Context, msg: FeedbackMessage) {
+        let result = self.handler.on_feedback(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_reveal_ranking(&self, ctx: Context, msg: RevealRankingMessage) {
+        let result = self.handler.on_reveal_ranking(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_game_over(&self, ctx: Context, msg: Box<GameOverMessage>) {
+        let result = self.handler.on_game_over(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_play_again(&self, ctx: Context, msg: PlayAgainMessage) {
+        let result = self.handler.on_play_again(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_answer_selected(&self, ctx: Context, msg: AnswerSelectedMessage) {
+        let result = self.handler.on_answer_selected(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_answer_response(&self, ctx: Context, msg: AnswerResponseMessage) {
+        let result = self.handler.on_answer_response(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_reset_controller(&self, ctx: Context, msg: ResetControllerMessage) {
+        let result = self.handler.on_reset_controller(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_submit_feedback(&self, ctx: Context, msg: SubmitFeedbackMessage) {
+        let result = self.handler.on_submit_feedback(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_username_rejected(&self, ctx: Context, msg: UsernameRejectedMessage) {
+        let result = self.handler.on_username_rejected(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_game_block_start(&self, ctx: Context, msg: GameBlockStartMessage) {
+        let result = self.handler.on_game_block_start(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_game_block_end(&self, ctx: Context, msg: GameBlockEndMessage) {
+        let result = self.handler.on_game_block_end(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_game_block_answer(&self, ctx: Context, msg: GameBlockAnswerMessage) {
+        let result = self.handler.on_game_block_answer(ctx.clone(), msg).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_unknown_message(&self, ctx: Context, value: serde_json::Value) {
+        let result = self.handler.on_unknown_message(ctx.clone(), value).await;
+        self.handle_result(ctx, result).await;
+    }
+
+    async fn on_error(&self, ctx: Context, e: KahootError) {
+        self.handler.on_error(ctx, e).await;
+    }
+}
@@ -0,0 +1,105 @@
+/// Where the clients find the kahoot servers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    /// The base url of the session reservation endpoint. The game code is appended to it.
+    pub reserve_url: String,
+
+    /// The base url of the cometd endpoint. The game code and session token are appended to it.
+    pub cometd_url: String,
+
+    /// The host sent in login and answer packets
+    pub host: String,
+}
+
+impl Endpoints {
+    /// The endpoints of kahoot.it
+    pub fn new() -> Self {
+        Self {
+            reserve_url: "https://kahoot.it/reserve/session".to_string(),
+            cometd_url: "wss://kahoot.it/cometd".to_string(),
+            host: "kahoot.it".to_string(),
+        }
+    }
+
+    /// Set the base url of the session reservation endpoint
+    pub fn reserve_url(mut self, reserve_url: impl Into<String>) -> Self {
+        self.reserve_url = reserve_url.into();
+        self
+    }
+
+    /// Set the base url of the cometd endpoint
+    pub fn cometd_url(mut self, cometd_url: impl Into<String>) -> Self {
+        self.cometd_url = cometd_url.into();
+        self
+    }
+
+    /// Set the host sent in login and answer packets
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    /// The url to probe a game code at
+    pub fn reserve_session_url(&self, code: &str) -> String {
+        format!("{}/{}/", self.reserve_url.trim_end_matches('/'), code)
+    }
+
+    /// The url of the cometd endpoint for a game
+    pub fn cometd_session_url(&self, code: &str, token: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            self.cometd_url.trim_end_matches('/'),
+            code,
+            token
+        )
+    }
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_urls() {
+        let endpoints = Endpoints::default();
+        assert_eq!(endpoints.host, "kahoot.it");
+        assert_eq!(
+            endpoints.reserve_session_url("123456"),
+            "https://kahoot.it/reserve/session/123456/"
+        );
+        assert_eq!(
+            endpoints.cometd_session_url("123456", "abc"),
+            "wss://kahoot.it/cometd/123456/abc"
+        );
+    }
+
+    #[test]
+    fn custom_urls() {
+        let endpoints = Endpoints::new()
+            .reserve_url("http://127.0.0.1:8080/reserve/session/")
+            .cometd_url("ws://127.0.0.1:8080/cometd/")
+            .host("127.0.0.1:8080");
+        assert_eq!(endpoints.host, "127.0.0.1:8080");
+        assert_eq!(
+            endpoints.reserve_session_url("123456"),
+            "http://127.0.0.1:8080/reserve/session/123456/"
+        );
+        assert_eq!(
+            endpoints.cometd_session_url("123456", "abc"),
+            "ws://127.0.0.1:8080/cometd/123456/abc"
+        );
+
+        let endpoints = Endpoints::new().cometd_url("ws://localhost/cometd");
+        assert_eq!(
+            endpoints.cometd_session_url("1", "t"),
+            "ws://localhost/cometd/1/t"
+        );
+        assert_eq!(endpoints.reserve_url, Endpoints::new().reserve_url);
+    }
+}
@@ -51,6 +51,29 @@ pub enum KahootError {
     MissingName,
 
     /// Invalid Login
-    #[error("invalid login")]
-    InvalidLogin(crate::LoginResponse),
+    #[error("invalid login: {0}")]
+    InvalidLogin(crate::LoginRejection),
+
+    /// Joining the game took too long
+    #[error("timed out joining after {0:?}")]
+    JoinTimeout(std::time::Duration),
+
+    /// There is no question to answer
+    #[error("no question to answer")]
+    NoQuestion,
+
+    /// The answer does not fit the current question
+    #[error("a {answer:?} answer does not fit a '{expected}' question")]
+    WrongAnswerType {
+        expected: crate::message::GameBlockType,
+        answer: crate::Answer,
+    },
+
+    /// The chosen answer does not exist
+    #[error("choice {choice} is out of range for a question with {choices} choices")]
+    InvalidChoice { choice: usize, choices: usize },
+
+    /// A puzzle order that is not an ordering of every choice
+    #[error("invalid puzzle order {0:?}")]
+    InvalidPuzzleOrder(Vec<usize>),
 }
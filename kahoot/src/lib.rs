@@ -1,22 +1,35 @@
+pub mod answer;
 pub mod challenge;
 pub mod client;
+pub mod endpoints;
 pub mod error;
 pub mod message;
+pub mod state;
 
 pub use crate::{
+    answer::Answer,
     client::{
         Client,
+        ClientBuilder,
         Context,
+        DefaultHandler,
+        ErrorPolicy,
+        Fallible,
         Handler,
+        TryHandler,
     },
+    endpoints::Endpoints,
     error::{
         KahootError,
         KahootResult,
     },
     message::Message,
+    state::GameState,
 };
 pub use async_trait::async_trait;
 
+use crate::challenge::GameMode;
+
 const USER_AGENT_STR: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/80.0.3987.132 Safari/537.36";
 
 #[derive(Debug)]
@@ -25,9 +38,17 @@ pub struct LoginResponse {
     pub description: Option<String>,
 
     pub cid: Option<String>,
+
+    /// How the game is played, if the server said
+    pub game_mode: Option<GameMode>,
 }
 
 impl LoginResponse {
+    /// Whether players join in teams
+    pub fn team_mode(&self) -> bool {
+        self.game_mode == Some(GameMode::Team)
+    }
+
     pub fn from_value(value: &serde_json::Value) -> Option<Self> {
         if value.get("type")?.as_str()? != "loginResponse" {
             return None;
@@ -48,10 +69,132 @@ impl LoginResponse {
             .and_then(|s| s.as_str())
             .map(|s| s.to_string());
 
+        let game_mode = value
+            .get("gameMode")
+            .and_then(|s| s.as_str())
+            .map(|s| GameMode::from(s.to_string()));
+
         Some(Self {
             error,
             description,
             cid,
+            game_mode,
         })
     }
 }
+
+/// Why a login was rejected.
+///
+/// Every variant keeps the raw response, so the server's own description is never lost.
+#[derive(Debug)]
+pub enum LoginRejection {
+    /// Another player already has the name
+    DuplicateName(LoginResponse),
+
+    /// The game is not accepting new players
+    GameLocked(LoginResponse),
+
+    /// There is no game with the code
+    GameNotFound(LoginResponse),
+
+    /// The name was caught by the game's name filter
+    NameFiltered(LoginResponse),
+
+    /// Any other rejection
+    Other(LoginResponse),
+}
+
+impl LoginRejection {
+    /// Classify a login response that has an error.
+    ///
+    /// Only the "Duplicate name" description, as sent by `kahoot-mock`, is exercised end to end.
+    /// The `LOCKED` and `NONEXISTING_SESSION` error codes and the other description matches are
+    /// unconfirmed guesses at what kahoot sends, so check [`LoginRejection::description`] when they matter.
+    pub fn from_response(response: LoginResponse) -> Self {
+        let error = response.error.as_deref().unwrap_or_default();
+        let description = response
+            .description
+            .as_deref()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        if description == "duplicate name" {
+            Self::DuplicateName(response)
+        } else if error == "LOCKED" || description.contains("locked") {
+            Self::GameLocked(response)
+        } else if error == "NONEXISTING_SESSION" || description.contains("not found") {
+            Self::GameNotFound(response)
+        } else if description.contains("filter") || description.contains("inappropriate") {
+            Self::NameFiltered(response)
+        } else {
+            Self::Other(response)
+        }
+    }
+
+    /// The raw login response
+    pub fn response(&self) -> &LoginResponse {
+        match self {
+            Self::DuplicateName(response)
+            | Self::GameLocked(response)
+            | Self::GameNotFound(response)
+            | Self::NameFiltered(response)
+            | Self::Other(response) => response,
+        }
+    }
+
+    /// The description the server gave, or its error code if there was none
+    pub fn description(&self) -> Option<&str> {
+        let response = self.response();
+        response
+            .description
+            .as_deref()
+            .or(response.error.as_deref())
+    }
+}
+
+impl std::fmt::Display for LoginRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let kind = match self {
+            Self::DuplicateName(_) => "duplicate name",
+            Self::GameLocked(_) => "game locked",
+            Self::GameNotFound(_) => "game not found",
+            Self::NameFiltered(_) => "name filtered",
+            Self::Other(_) => return f.write_str(self.description().unwrap_or("unknown reason")),
+        };
+
+        match self.description() {
+            Some(description) => write!(f, "{} ({})", kind, description),
+            None => f.write_str(kind),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rejection(error: &str, description: &str) -> LoginRejection {
+        LoginRejection::from_response(LoginResponse {
+            error: Some(error.to_string()),
+            description: Some(description.to_string()),
+            cid: None,
+            game_mode: None,
+        })
+    }
+
+    #[test]
+    fn login_rejection_keeps_description() {
+        let duplicate = rejection("USER_INPUT", "Duplicate name");
+        assert!(matches!(duplicate, LoginRejection::DuplicateName(_)));
+        assert_eq!(duplicate.description(), Some("Duplicate name"));
+        assert_eq!(duplicate.to_string(), "duplicate name (Duplicate name)");
+
+        let filtered = rejection("USER_INPUT", "Name filtered");
+        assert!(matches!(filtered, LoginRejection::NameFiltered(_)));
+        assert_eq!(filtered.description(), Some("Name filtered"));
+
+        let other = rejection("USER_INPUT", "Something else");
+        assert!(matches!(other, LoginRejection::Other(_)));
+        assert_eq!(other.to_string(), "Something else");
+    }
+}
@@ -87,6 +87,30 @@ pub enum Message {
         msg: UsernameAcceptedMessage,
         cid: String,
     },
+    AnswerSelected {
+        msg: AnswerSelectedMessage,
+    },
+    AnswerResponse {
+        msg: AnswerResponseMessage,
+    },
+    ResetController {
+        msg: ResetControllerMessage,
+    },
+    SubmitFeedback {
+        msg: SubmitFeedbackMessage,
+    },
+    UsernameRejected {
+        msg: UsernameRejectedMessage,
+    },
+    GameBlockStart {
+        msg: GameBlockStartMessage,
+    },
+    GameBlockEnd {
+        msg: GameBlockEndMessage,
+    },
+    GameBlockAnswer {
+        msg: GameBlockAnswerMessage,
+    },
     Unknown(serde_json::Value),
 }
 
@@ -236,18 +260,166 @@ impl Message {
 
                 Self::UsernameAccepted { msg, cid }
             }
-            _ => Self::Unknown(value),
+            MessageType::AnswerSelected => {
+                let msg = match serde_json::from_str::<AnswerSelectedMessage>(content) {
+                    Ok(c) => c,
+                    Err(_) => {
+                        return Self::Unknown(value);
+                    }
+                };
+
+                Self::AnswerSelected { msg }
+            }
+            MessageType::AnswerResponse => {
+                let msg = match serde_json::from_str::<AnswerResponseMessage>(content) {
+                    Ok(c) => c,
+                    Err(_) => {
+                        return Self::Unknown(value);
+                    }
+                };
+
+                Self::AnswerResponse { msg }
+            }
+            MessageType::ResetController => {
+                let msg = match serde_json::from_str::<ResetControllerMessage>(content) {
+                    Ok(c) => c,
+                    Err(_) => {
+                        return Self::Unknown(value);
+                    }
+                };
+
+                Self::ResetController { msg }
+            }
+            MessageType::SubmitFeedback => {
+                let msg = match serde_json::from_str::<SubmitFeedbackMessage>(content) {
+                    Ok(c) => c,
+                    Err(_) => {
+                        return Self::Unknown(value);
+                    }
+                };
+
+                Self::SubmitFeedback { msg }
+            }
+            MessageType::UsernameRejected => {
+                let msg = match serde_json::from_str::<UsernameRejectedMessage>(content) {
+                    Ok(c) => c,
+                    Err(_) => {
+                        return Self::Unknown(value);
+                    }
+                };
+
+                Self::UsernameRejected { msg }
+            }
+            MessageType::GameBlockStart => {
+                let msg = match serde_json::from_str::<GameBlockStartMessage>(content) {
+                    Ok(c) => c,
+                    Err(_) => {
+                        return Self::Unknown(value);
+                    }
+                };
+
+                Self::GameBlockStart { msg }
+            }
+            MessageType::GameBlockEnd => {
+                let msg = match serde_json::from_str::<GameBlockEndMessage>(content) {
+                    Ok(c) => c,
+                    Err(_) => {
+                        return Self::Unknown(value);
+                    }
+                };
+
+                Self::GameBlockEnd { msg }
+            }
+            MessageType::GameBlockAnswer => {
+                let msg = match serde_json::from_str::<GameBlockAnswerMessage>(content) {
+                    Ok(c) => c,
+                    Err(_) => {
+                        return Self::Unknown(value);
+                    }
+                };
+
+                Self::GameBlockAnswer { msg }
+            }
+        }
+    }
+}
+
+/// The kind of a question, or other game block
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub enum GameBlockType {
+    /// A single choice quiz, which includes true/false questions
+    Quiz,
+    MultipleSelectQuiz,
+    /// A single choice poll
+    Survey,
+    MultipleSelectPoll,
+    /// A type answer question
+    OpenEnded,
+    /// A puzzle, answered by ordering the choices
+    Jumble,
+    Slider,
+    WordCloud,
+    /// An open-ended poll
+    OpenQuestion,
+    Brainstorming,
+    /// A slide with nothing to answer
+    Content,
+    Other(String),
+}
+
+impl GameBlockType {
+    /// The name kahoot uses for this block type
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Quiz => "quiz",
+            Self::MultipleSelectQuiz => "multiple_select_quiz",
+            Self::Survey => "survey",
+            Self::MultipleSelectPoll => "multiple_select_poll",
+            Self::OpenEnded => "open_ended",
+            Self::Jumble => "jumble",
+            Self::Slider => "slider",
+            Self::WordCloud => "word_cloud",
+            Self::OpenQuestion => "open_question",
+            Self::Brainstorming => "brainstorming",
+            Self::Content => "content",
+            Self::Other(s) => s,
+        }
+    }
+}
+
+impl From<String> for GameBlockType {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "quiz" => Self::Quiz,
+            "multiple_select_quiz" => Self::MultipleSelectQuiz,
+            "survey" => Self::Survey,
+            "multiple_select_poll" => Self::MultipleSelectPoll,
+            "open_ended" => Self::OpenEnded,
+            "jumble" => Self::Jumble,
+            "slider" => Self::Slider,
+            "word_cloud" => Self::WordCloud,
+            "open_question" => Self::OpenQuestion,
+            "brainstorming" => Self::Brainstorming,
+            "content" => Self::Content,
+            _ => Self::Other(s),
         }
     }
 }
 
+impl std::fmt::Display for GameBlockType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[derive(Deserialize, Debug)]
 pub struct GetReadyMessage {
     #[serde(rename = "questionIndex")]
     pub question_index: usize,
 
     #[serde(rename = "gameBlockType")]
-    game_block_type: String,
+    pub game_block_type: GameBlockType,
 
     #[serde(rename = "gameBlockLayout")]
     game_block_layout: Option<String>,
@@ -268,7 +440,7 @@ pub struct StartQuestionMessage {
     pub question_index: usize,
 
     #[serde(rename = "gameBlockType")]
-    game_block_type: String,
+    pub game_block_type: GameBlockType,
 
     #[serde(rename = "quizQuestionAnswers")]
     pub quiz_question_answers: Vec<usize>,
@@ -420,7 +592,7 @@ pub struct StartQuizMessage {
     pub quiz_name: String,
 
     #[serde(rename = "quizType")]
-    quiz_type: String,
+    pub quiz_type: String,
 
     #[serde(rename = "quizQuestionAnswers")]
     pub quiz_question_answers: Vec<usize>,
@@ -464,3 +636,101 @@ pub struct UsernameAcceptedMessage {
     #[serde(flatten)]
     pub extra: HashMap<String, serde_json::Value>,
 }
+
+// No captured payloads back the messages below yet, so every named field is optional
+// and anything else is kept in `extra`.
+
+#[derive(Deserialize, Debug)]
+pub struct AnswerSelectedMessage {
+    #[serde(rename = "questionIndex")]
+    pub question_index: Option<usize>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AnswerResponseMessage {
+    #[serde(rename = "primaryMessage")]
+    pub primary_message: Option<String>,
+
+    #[serde(rename = "secondaryMessage")]
+    pub secondary_message: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ResetControllerMessage {
+    #[serde(rename = "kickCode")]
+    pub kick_code: Option<u64>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SubmitFeedbackMessage {
+    pub fun: Option<i64>,
+    pub learning: Option<i64>,
+    pub recommend: Option<i64>,
+    pub overall: Option<i64>,
+
+    #[serde(rename = "totalScore")]
+    pub total_score: Option<u64>,
+
+    pub nickname: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UsernameRejectedMessage {
+    #[serde(rename = "playerName")]
+    pub player_name: Option<String>,
+
+    pub description: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GameBlockStartMessage {
+    #[serde(rename = "questionIndex")]
+    pub question_index: Option<usize>,
+
+    #[serde(rename = "gameBlockType")]
+    pub game_block_type: Option<GameBlockType>,
+
+    #[serde(rename = "timeAvailable")]
+    pub time_available: Option<u64>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GameBlockEndMessage {
+    #[serde(rename = "questionIndex")]
+    pub question_index: Option<usize>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GameBlockAnswerMessage {
+    /// The answer, whose shape depends on the game block type
+    pub choice: Option<serde_json::Value>,
+
+    #[serde(rename = "questionIndex")]
+    pub question_index: Option<usize>,
+
+    pub meta: Option<serde_json::Value>,
+
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
@@ -0,0 +1,104 @@
+use crate::{
+    message::GameBlockType,
+    Message,
+};
+
+/// What a client knows about the game it is playing, built from the messages it received
+#[derive(Debug, Clone, Default)]
+pub struct GameState {
+    /// The name of the quiz
+    pub quiz_name: Option<String>,
+
+    /// The type of the quiz
+    pub quiz_type: Option<String>,
+
+    /// The index of the current question
+    pub question_index: Option<usize>,
+
+    /// The block type of the current question
+    pub block_type: Option<GameBlockType>,
+
+    /// The number of choices of each question
+    pub answer_counts: Vec<usize>,
+
+    /// Whether the current question was answered
+    pub answered: bool,
+
+    /// The time left before the current question starts, as sent with `GetReady`
+    pub time_left: Option<usize>,
+
+    /// The points given for the last revealed answer
+    pub last_points: Option<u64>,
+
+    /// The answer streak after the last revealed answer
+    pub streak: Option<u64>,
+
+    /// The total score
+    pub total_score: u64,
+
+    /// The rank among all players
+    pub rank: Option<u64>,
+}
+
+impl GameState {
+    /// The number of choices of the current question, if it has any
+    pub fn answer_count(&self) -> Option<usize> {
+        self.answer_counts.get(self.question_index?).copied()
+    }
+
+    /// Update the state from a received message
+    pub(crate) fn apply(&mut self, message: &Message) {
+        match message {
+            Message::StartQuiz { msg } => {
+                // A quiz that is played again starts from scratch
+                *self = GameState {
+                    quiz_name: Some(msg.quiz_name.clone()),
+                    quiz_type: Some(msg.quiz_type.clone()),
+                    answer_counts: msg.quiz_question_answers.clone(),
+                    ..GameState::default()
+                };
+            }
+            Message::GetReady { msg } => {
+                self.start_question(msg.question_index, &msg.game_block_type);
+                self.answer_counts = msg.quiz_question_answers.clone();
+                self.time_left = Some(msg.time_left);
+            }
+            Message::StartQuestion { msg } => {
+                self.start_question(msg.question_index, &msg.game_block_type);
+                self.answer_counts = msg.quiz_question_answers.clone();
+            }
+            Message::GameBlockStart { msg } => {
+                if let (Some(question_index), Some(block_type)) =
+                    (msg.question_index, &msg.game_block_type)
+                {
+                    self.start_question(question_index, block_type);
+                }
+            }
+            Message::RevealAnswer { msg } => {
+                self.last_points = Some(msg.points);
+                self.streak = msg
+                    .points_data
+                    .answer_streak_points
+                    .get("streakLevel")
+                    .and_then(|level| level.as_u64());
+                self.total_score = msg.total_score;
+                self.rank = Some(msg.rank);
+            }
+            Message::GameOver { msg, .. } => {
+                self.total_score = msg.total_score;
+                self.rank = Some(msg.rank);
+            }
+            _ => {}
+        }
+    }
+
+    /// Move to a question, forgetting the answer to the last one
+    fn start_question(&mut self, question_index: usize, block_type: &GameBlockType) {
+        if self.question_index != Some(question_index) {
+            self.answered = false;
+        }
+
+        self.question_index = Some(question_index);
+        self.block_type = Some(block_type.clone());
+    }
+}
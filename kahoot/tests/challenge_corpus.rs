@@ -0,0 +1,106 @@
+//! Checks every enabled challenge solver against the recorded challenges in `tests/challenges`.
+//!
+//! Record new samples with `cargo run --example record_challenge -- <code>`.
+//! Each sample's `expected` token is the one the live server accepted, not a solver's output,
+//! so do not write samples by hand or fill in `expected` from a solver.
+
+use kahoot::challenge::{
+    decode_with,
+    ChallengeSolver,
+    NativeSolver,
+};
+use serde::Deserialize;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+/// A recorded challenge
+#[derive(Debug, Deserialize)]
+struct Sample {
+    /// The encoded session token
+    token: String,
+
+    /// The challenge js
+    challenge: String,
+
+    /// The decoded session token the server accepted
+    expected: String,
+}
+
+fn corpus_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/challenges")
+}
+
+fn load_corpus() -> Vec<(String, Sample)> {
+    let mut paths: Vec<_> = std::fs::read_dir(corpus_dir())
+        .expect("read corpus dir")
+        .map(|entry| entry.expect("read corpus entry").path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let data = std::fs::read(&path).expect("read sample");
+            let sample = serde_json::from_slice(&data)
+                .unwrap_or_else(|e| panic!("invalid sample '{}': {}", name, e));
+
+            (name, sample)
+        })
+        .collect()
+}
+
+/// Run a solver over the whole corpus, reporting every failing sample at once
+fn check_solver(solver: &dyn ChallengeSolver) {
+    let corpus = load_corpus();
+    assert!(!corpus.is_empty(), "the challenge corpus is empty");
+
+    let failures: Vec<String> = corpus
+        .iter()
+        .filter_map(
+            |(name, sample)| match decode_with(solver, &sample.token, &sample.challenge) {
+                Ok(token) if token == sample.expected => None,
+                Ok(token) => Some(format!(
+                    "{}: expected '{}', got '{}'",
+                    name, sample.expected, token
+                )),
+                Err(e) => Some(format!("{}: {}", name, e)),
+            },
+        )
+        .collect();
+
+    assert!(
+        failures.is_empty(),
+        "solver '{}' failed {}/{} samples:\n{}",
+        solver.name(),
+        failures.len(),
+        corpus.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn native_corpus() {
+    check_solver(&NativeSolver);
+}
+
+#[test]
+#[cfg(feature = "ducc")]
+fn ducc_corpus() {
+    check_solver(&kahoot::challenge::DuccSolver::new());
+}
+
+#[test]
+#[ignore]
+#[cfg(feature = "boa")]
+fn boa_corpus() {
+    check_solver(&kahoot::challenge::BoaSolver);
+}
+
+#[test]
+fn default_solver_corpus() {
+    check_solver(&kahoot::challenge::default_solver());
+}
@@ -0,0 +1,246 @@
+//! Parses the player message payloads in `tests/synthetic_messages`. See the README there for where they come from.
+
+use kahoot::Message;
+use std::path::Path;
+
+/// Load a fixture and parse it as a player message
+fn parse(name: &str) -> Message {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/synthetic_messages")
+        .join(format!("{}.json", name));
+    let data = std::fs::read(&path).expect("read fixture");
+    let value = serde_json::from_slice(&data)
+        .unwrap_or_else(|e| panic!("invalid fixture '{}': {}", name, e));
+
+    Message::from_value(value)
+}
+
+#[test]
+fn get_ready() {
+    match parse("get_ready") {
+        Message::GetReady { msg } => {
+            assert_eq!(msg.question_index, 0);
+            assert_eq!(msg.quiz_question_answers, [4, 2]);
+            assert_eq!(msg.time_left, 5);
+        }
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn start_question() {
+    match parse("start_question") {
+        Message::StartQuestion { msg } => {
+            assert_eq!(msg.question_index, 0);
+            assert_eq!(msg.quiz_question_answers, [4, 2]);
+        }
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn game_over() {
+    match parse("game_over") {
+        Message::GameOver { msg, cid } => {
+            assert_eq!(msg.cid, cid);
+            assert_eq!(msg.rank, 1);
+            assert_eq!(msg.correct_count, 1);
+            assert_eq!(msg.incorrect_count, 1);
+            assert_eq!(msg.player_count, 3);
+            assert_eq!(msg.total_score, 950);
+        }
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn time_up() {
+    match parse("time_up") {
+        Message::TimeUp { msg } => assert_eq!(msg.question_number, 0),
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn play_again() {
+    match parse("play_again") {
+        Message::PlayAgain { msg } => assert!(msg.extra.is_empty()),
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn answer_selected() {
+    match parse("answer_selected") {
+        Message::AnswerSelected { msg } => assert_eq!(msg.question_index, Some(0)),
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn answer_response() {
+    match parse("answer_response") {
+        Message::AnswerResponse { msg } => {
+            assert_eq!(
+                msg.primary_message.as_deref(),
+                Some("Were you tooooooo fast?")
+            );
+            assert_eq!(msg.secondary_message, None);
+        }
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn reveal_answer() {
+    match parse("reveal_answer") {
+        Message::RevealAnswer { msg } => {
+            assert_eq!(msg.choice, 1);
+            assert!(msg.is_correct);
+            assert_eq!(msg.points, 950);
+            assert_eq!(msg.correct_answers, ["Blue"]);
+            assert_eq!(msg.points_data.question_points, 950);
+        }
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn start_quiz() {
+    match parse("start_quiz") {
+        Message::StartQuiz { msg } => {
+            assert_eq!(msg.quiz_name, "Mock Quiz");
+            assert_eq!(msg.quiz_question_answers, [4, 2]);
+        }
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn reset_controller() {
+    match parse("reset_controller") {
+        Message::ResetController { msg } => assert_eq!(msg.kick_code, Some(1)),
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn submit_feedback() {
+    match parse("submit_feedback") {
+        Message::SubmitFeedback { msg } => {
+            assert_eq!(msg.fun, Some(5));
+            assert_eq!(msg.learning, Some(1));
+            assert_eq!(msg.recommend, Some(1));
+            assert_eq!(msg.overall, Some(1));
+            assert_eq!(msg.nickname.as_deref(), Some("bot"));
+        }
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn feedback() {
+    match parse("feedback") {
+        Message::Feedback { .. } => {}
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn reveal_ranking() {
+    match parse("reveal_ranking") {
+        Message::RevealRanking { cid, .. } => assert!(!cid.is_empty()),
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn username_accepted() {
+    match parse("username_accepted") {
+        Message::UsernameAccepted { msg, .. } => {
+            assert_eq!(msg.player_name, "bot");
+            assert!(msg.player_v2);
+        }
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn username_rejected() {
+    match parse("username_rejected") {
+        Message::UsernameRejected { msg } => {
+            assert_eq!(msg.player_name.as_deref(), Some("bot"));
+            assert_eq!(msg.description.as_deref(), Some("Duplicate name"));
+        }
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn game_block_start() {
+    match parse("game_block_start") {
+        Message::GameBlockStart { msg } => {
+            assert_eq!(msg.question_index, Some(0));
+            assert_eq!(msg.time_available, Some(20000));
+        }
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn game_block_end() {
+    match parse("game_block_end") {
+        Message::GameBlockEnd { msg } => assert_eq!(msg.question_index, Some(0)),
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn game_block_answer() {
+    match parse("game_block_answer") {
+        Message::GameBlockAnswer { msg } => {
+            assert_eq!(msg.choice, Some(1.into()));
+            assert_eq!(msg.question_index, Some(0));
+        }
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn unverified_fields_optional() {
+    for id in &[6, 7, 10, 11, 15, 43, 44, 45] {
+        let value = serde_json::json!({
+            "type": "message",
+            "id": id,
+            "content": "{\"unexpected\":true}",
+        });
+
+        match Message::from_value(value) {
+            Message::Unknown(value) => panic!("failed to parse id {}: {}", id, value),
+            Message::SubmitFeedback { msg } => {
+                assert_eq!(msg.fun, None);
+                assert_eq!(msg.extra["unexpected"], true);
+            }
+            Message::GameBlockStart { msg } => {
+                assert_eq!(msg.question_index, None);
+                assert!(msg.game_block_type.is_none());
+            }
+            Message::GameBlockAnswer { msg } => assert_eq!(msg.choice, None),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn unknown_id() {
+    let value = serde_json::json!({
+        "type": "message",
+        "id": 99,
+        "content": "{}",
+    });
+
+    match Message::from_value(value.clone()) {
+        Message::Unknown(unknown) => assert_eq!(unknown, value),
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}